@@ -18,4 +18,5 @@ extern crate lazy_static;
 
 pub mod tcs;
 pub mod debug;
+pub mod stdio;
 pub mod util;
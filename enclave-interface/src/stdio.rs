@@ -0,0 +1,39 @@
+/*
+ * Interface to interact with libenclave-based secure enclaves.
+ *
+ * (C) Copyright 2016 Jethro G. Beekman
+ *
+ * This program is free software; you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation; either version 2 of the License, or (at your option)
+ * any later version.
+ */
+
+use std::io::Error as IoError;
+
+use libc;
+
+/// Usercall numbers reserved by libenclave for standard I/O. These must match
+/// the values in libenclave's `usercall` module.
+pub const USERCALL_READ:  u64 = 0x7fff_ffff_ffff_0000;
+pub const USERCALL_WRITE: u64 = 0x7fff_ffff_ffff_0001;
+
+/// Handle the usercalls made by libenclave's `io::stdin`, `io::stdout` and
+/// `io::stderr` using this process' standard streams.
+///
+/// Returns `None` if `nr` is not one of these usercalls, so that this can be
+/// tried first from the closure passed to `tcs::enter`.
+pub fn handle_usercall(nr: u64, fd: u64, buf: u64, len: u64, _p4: u64) -> Option<u64> {
+	let ret=match (nr,fd) {
+		(USERCALL_READ,0) => unsafe{libc::read(0,buf as *mut libc::c_void,len as libc::size_t)},
+		(USERCALL_WRITE,1) | (USERCALL_WRITE,2) => unsafe{libc::write(fd as libc::c_int,buf as *const libc::c_void,len as libc::size_t)},
+		(USERCALL_READ,_) | (USERCALL_WRITE,_) => return Some((-libc::EBADF) as i64 as u64),
+		_ => return None,
+	};
+	if ret<0 {
+		let errno=IoError::last_os_error().raw_os_error().unwrap_or(libc::EIO);
+		Some((-errno) as i64 as u64)
+	} else {
+		Some(ret as u64)
+	}
+}
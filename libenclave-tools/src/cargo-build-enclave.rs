@@ -10,12 +10,14 @@
  */
 
 #![feature(custom_derive, plugin)]
-#![plugin(serde_macros)]
+#![cfg_attr(feature="with-serde",plugin(serde_macros))]
 
 #[macro_use]
 extern crate clap;
 extern crate libc;
+#[cfg(feature="with-serde")]
 extern crate serde;
+#[cfg(feature="with-serde")]
 extern crate serde_json;
 
 mod naming;
@@ -33,20 +35,23 @@ use std::borrow::Cow;
 
 use clap::ArgMatches;
 
+#[cfg(feature="with-serde")]
 use serde_json::error::Error as JsonError;
 
 use exec::{CommandExt,ExecError};
 use num::NumArg;
 
+#[cfg(feature="with-serde")]
 trait JsonDeserialize: serde::Deserialize {
 	fn from_json_slice(v: &[u8]) -> Result<Self,JsonError> {
 		serde_json::from_slice(v)
 	}
 }
 
+#[cfg(feature="with-serde")]
 impl<T: serde::Deserialize> JsonDeserialize for T {}
 
-#[derive(Deserialize)]
+#[cfg_attr(feature="with-serde",derive(Deserialize))]
 struct Manifest {
 	name: String,
 	id: String,
@@ -56,13 +61,13 @@ struct Manifest {
 	features: HashMap<String,Vec<String>>,
 }
 
-#[derive(Deserialize)]
+#[cfg_attr(feature="with-serde",derive(Deserialize))]
 struct ManifestTarget {
 	name: String,
 	kind: Vec<String>,
 }
 
-#[derive(Deserialize)]
+#[cfg_attr(feature="with-serde",derive(Deserialize))]
 struct ManifestDependency {
 	name: String,
 	req: String,
@@ -113,7 +118,10 @@ enum Error {
 	StdoutError(IoError),
 	CargoReadManifestInvalidCmdline,
 	CargoReadManifestExec(ExecError),
+	#[cfg(feature="with-serde")]
 	CargoReadManifestJson(JsonError),
+	#[cfg(not(feature="with-serde"))]
+	CargoReadManifestUnsupported,
 	CargoBuildInvalidCmdline,
 	CargoBuildExec(ExecError),
 	CargoBuildNoOutput(IoError),
@@ -127,9 +135,17 @@ struct BuilderMode<'args> {
 	verbose: bool,
 	color: bool,
 	quiet: bool,
-	ssaframesize: u32,
-	heap_size: u64,
-	stack_size: u64,
+	ssaframesize: Option<u32>,
+	heap_size: Option<u64>,
+	heap_addr: Option<u64>,
+	heap_align: Option<u64>,
+	stack_size: Option<u64>,
+	tls_size: Option<u64>,
+	extra_heaps: Vec<u64>,
+	extra_ssa_frames: Option<u32>,
+	tcs_alignment: Option<u64>,
+	tcs_guard_size: Option<u64>,
+	debug_trap_at_entry: bool,
 	cargo_args: Vec<Cow<'args,OsStr>>,
 }
 
@@ -152,9 +168,17 @@ impl<'args> BuilderMode<'args> {
 			verbose: args.is_present("verbose"),
 			quiet: args.is_present("quiet"),
 			color: color_detect(args.value_of("color").unwrap()),
-			ssaframesize: u32::parse_arg(args.value_of("ssaframesize").unwrap()),
-			heap_size: u64::parse_arg(args.value_of("heap-size").unwrap()),
-			stack_size: u64::parse_arg(args.value_of("stack-size").unwrap()),
+			ssaframesize: args.value_of("ssaframesize").map(u32::parse_arg),
+			heap_size: args.value_of("heap-size").map(u64::parse_arg),
+			heap_addr: args.value_of("heap-addr").map(u64::parse_arg),
+			heap_align: args.value_of("heap-align").map(u64::parse_arg),
+			stack_size: args.value_of("stack-size").map(u64::parse_arg),
+			tls_size: args.value_of("tls-size").map(u64::parse_arg),
+			extra_heaps: args.values_of("extra-heap").map(|sizes|sizes.map(u64::parse_arg).collect()).unwrap_or(vec![]),
+			extra_ssa_frames: args.value_of("extra-ssa-frames").map(u32::parse_arg),
+			tcs_alignment: args.value_of("tcs-alignment").map(u64::parse_arg),
+			tcs_guard_size: args.value_of("tcs-guard-size").map(u64::parse_arg),
+			debug_trap_at_entry: args.is_present("debug-trap-at-entry"),
 			cargo_args: args.values_of_os("cargo-opts").map(|args|args.map(Cow::Borrowed).collect()).unwrap_or(vec![]),
 		}
 	}
@@ -197,7 +221,18 @@ impl<'args> BuilderMode<'args> {
 		}
 
 		let out=try!(cargo.output_ext(self.verbose).map_err(Error::CargoReadManifestExec));
-		Manifest::from_json_slice(&out.stdout).map_err(Error::CargoReadManifestJson)
+		Self::parse_manifest(&out.stdout)
+	}
+
+	#[cfg(feature="with-serde")]
+	fn parse_manifest(json: &[u8]) -> Result<Manifest,Error> {
+		Manifest::from_json_slice(json).map_err(Error::CargoReadManifestJson)
+	}
+
+	/// The output of `cargo read-manifest` can only be parsed with serde
+	#[cfg(not(feature="with-serde"))]
+	fn parse_manifest(_json: &[u8]) -> Result<Manifest,Error> {
+		Err(Error::CargoReadManifestUnsupported)
 	}
 
 	fn target_path(&self, manifest: &Manifest) -> Result<OsString,Error> {
@@ -261,12 +296,47 @@ impl<'args> Builder<'args> {
 		let mut cmd=try!(Self::find_link_sgxs());
 
 		if self.mode.debug { cmd.arg("--debug"); }
-		cmd.arg("--ssaframesize");
-		cmd.arg(format!("0x{:x}",self.mode.ssaframesize));
-		cmd.arg("--heap-size");
-		cmd.arg(format!("0x{:x}",self.mode.heap_size));
-		cmd.arg("--stack-size");
-		cmd.arg(format!("0x{:x}",self.mode.stack_size));
+		if let Some(ssaframesize)=self.mode.ssaframesize {
+			cmd.arg("--ssaframesize");
+			cmd.arg(format!("0x{:x}",ssaframesize));
+		}
+		if let Some(heap_size)=self.mode.heap_size {
+			cmd.arg("--heap-size");
+			cmd.arg(format!("0x{:x}",heap_size));
+		}
+		if let Some(heap_addr)=self.mode.heap_addr {
+			cmd.arg("--heap-addr");
+			cmd.arg(format!("0x{:x}",heap_addr));
+		}
+		if let Some(heap_align)=self.mode.heap_align {
+			cmd.arg("--heap-align");
+			cmd.arg(format!("0x{:x}",heap_align));
+		}
+		if let Some(stack_size)=self.mode.stack_size {
+			cmd.arg("--stack-size");
+			cmd.arg(format!("0x{:x}",stack_size));
+		}
+		if let Some(tls_size)=self.mode.tls_size {
+			cmd.arg("--tls-size");
+			cmd.arg(format!("0x{:x}",tls_size));
+		}
+		for extra_heap in &self.mode.extra_heaps {
+			cmd.arg("--extra-heap");
+			cmd.arg(format!("0x{:x}",extra_heap));
+		}
+		if let Some(extra_ssa_frames)=self.mode.extra_ssa_frames {
+			cmd.arg("--extra-ssa-frames");
+			cmd.arg(format!("{}",extra_ssa_frames));
+		}
+		if let Some(tcs_alignment)=self.mode.tcs_alignment {
+			cmd.arg("--tcs-alignment");
+			cmd.arg(format!("0x{:x}",tcs_alignment));
+		}
+		if let Some(tcs_guard_size)=self.mode.tcs_guard_size {
+			cmd.arg("--tcs-guard-size");
+			cmd.arg(format!("0x{:x}",tcs_guard_size));
+		}
+		if self.mode.debug_trap_at_entry { cmd.arg("--debug-trap-at-entry"); }
 
 		cmd.arg(&self.staticlib_artifact);
 		cmd.status_ext(self.mode.verbose).map_err(Error::LinkExec)
@@ -302,15 +372,23 @@ fn main() {
 			.version(crate_version!())
 			.setting(AppSettings::UnifiedHelpMessage)
 			.setting(AppSettings::TrailingVarArg)
-			.usage("cargo build-enclave -H <heap-size> -S <stack-size> [OPTIONS] [--] [<opts for cargo build>...]")
+			.usage("cargo build-enclave [OPTIONS] [--] [<opts for cargo build>...]")
 			.arg(Arg::with_name("verbose").short("v").long("verbose").help("Use verbose output"))
 			.arg(Arg::with_name("quiet").short("q").long("quiet").help("No output printed to stdout"))
 			.arg(Arg::with_name("color").value_name("WHEN").possible_values(&["auto", "always", "never"]).default_value("auto").long("color").help("Coloring"))
 			.arg(Arg::with_name("debug").short("d").long("debug").help("Link with the debug runtime"))
 			.arg(Arg::with_name("cargo-opts").index(1).multiple(true).help("Options to be passed to `cargo build`"))
-			.arg(Arg::with_name("ssaframesize")         .long("ssaframesize").value_name("PAGES").validator(u32::validate_arg).default_value("1").help("Specify SSAFRAMESIZE"))
-			.arg(Arg::with_name("heap-size") .short("H").long("heap-size")   .value_name("BYTES").validator(u64::validate_arg).required(true)    .help("Specify heap size"))
-			.arg(Arg::with_name("stack-size").short("S").long("stack-size")  .value_name("BYTES").validator(u64::validate_arg).required(true)    .help("Specify stack size"))
+			.arg(Arg::with_name("ssaframesize")         .long("ssaframesize").value_name("PAGES").validator(u32::validate_arg).help("Specify SSAFRAMESIZE (default: 1)"))
+			.arg(Arg::with_name("heap-size") .short("H").long("heap-size")   .value_name("BYTES").validator(u64::validate_arg).help("Specify heap size"))
+			.arg(Arg::with_name("stack-size").short("S").long("stack-size")  .value_name("BYTES").validator(u64::validate_arg).help("Specify stack size"))
+			.arg(Arg::with_name("tls-size")             .long("tls-size").value_name("BYTES").validator(u64::validate_arg).help("Specify the TLS size (default: 0x1000)"))
+			.arg(Arg::with_name("heap-addr")            .long("heap-addr").value_name("ADDR").validator(u64::validate_arg).help("Place the heap at ADDR instead of after the ELF segments"))
+			.arg(Arg::with_name("heap-align")           .long("heap-align").value_name("BYTES").validator(u64::validate_arg).help("Align the heap base to BYTES (default: 0x1000)"))
+			.arg(Arg::with_name("extra-heap")           .long("extra-heap").value_name("BYTES").multiple(true).number_of_values(1).validator(u64::validate_arg).help("Add a heap of BYTES after the primary heap, passed in HEAP<n>_BASE/HEAP<n>_SIZE (n=2,3,...)"))
+			.arg(Arg::with_name("extra-ssa-frames")     .long("extra-ssa-frames").value_name("FRAMES").validator(u32::validate_arg).help("Allocate additional SSA frames not included in NSSA (default: 0)"))
+			.arg(Arg::with_name("tcs-alignment")        .long("tcs-alignment").value_name("BYTES").validator(u64::validate_arg).help("Align the TCS to BYTES (default: 0x1000)"))
+			.arg(Arg::with_name("tcs-guard-size")       .long("tcs-guard-size").value_name("BYTES").validator(u64::validate_arg).help("Leave BYTES of unmapped guard space before the TCS and after the SSA (default: 0)"))
+			.arg(Arg::with_name("debug-trap-at-entry")  .long("debug-trap-at-entry").requires("debug").help("Patch an int3 instruction over the first byte of the entry point"))
 		).get_matches();
 
 	let args=args.subcommand_matches("build-enclave").unwrap();
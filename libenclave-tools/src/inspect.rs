@@ -0,0 +1,262 @@
+/*
+ * Tools for building and linking enclaves using libenclave.
+ *
+ * (C) Copyright 2016 Jethro G. Beekman
+ *
+ * This program is free software; you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation; either version 2 of the License, or (at your option)
+ * any later version.
+ */
+
+use std::fmt;
+use std::io::{self,Read};
+use std::collections::HashMap;
+
+use sgx_isa::{PageType,SecinfoFlags,secinfo_flags};
+use sgxs_crate::sgxs::{self,SgxsRead,SgxsWrite,PageReader,PageChunks,Meas,MeasECreate,MeasEAdd,MeasuredData,SecinfoTruncated};
+
+/// Summary of a single EADD in an SGXS stream
+#[derive(Clone,Debug)]
+pub struct PageSummary {
+	pub offset: u64,
+	/// `None` if the page type is not a known `PageType`
+	pub page_type: Option<PageType>,
+	/// Permission flags, without the page type
+	pub flags: SecinfoFlags,
+	pub measured: PageChunks,
+}
+
+impl fmt::Display for PageSummary {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let page_type=match self.page_type {
+			Some(PageType::Secs) => "SECS",
+			Some(PageType::Tcs) => "TCS",
+			Some(PageType::Reg) => "REG",
+			Some(PageType::Va) => "VA",
+			Some(PageType::Trim) => "TRIM",
+			None => "?",
+		};
+		write!(f,"0x{:08x} {:<4} {}{}{} measured={}",self.offset,page_type,
+			if self.flags.contains(secinfo_flags::R) { "r" } else { "-" },
+			if self.flags.contains(secinfo_flags::W) { "w" } else { "-" },
+			if self.flags.contains(secinfo_flags::X) { "x" } else { "-" },
+			self.measured)
+	}
+}
+
+fn page_type(flags: SecinfoFlags) -> Option<PageType> {
+	match flags.page_type() {
+		0 => Some(PageType::Secs),
+		1 => Some(PageType::Tcs),
+		2 => Some(PageType::Reg),
+		3 => Some(PageType::Va),
+		4 => Some(PageType::Trim),
+		_ => None,
+	}
+}
+
+/// Parse an SGXS stream into its ECREATE parameters and a summary of every
+/// page, in stream order. The stream must be canonical.
+pub fn summarize<R: SgxsRead>(sgxs: &mut R) -> sgxs::Result<(MeasECreate,Vec<PageSummary>)> {
+	let (ecreate,mut reader)=try!(PageReader::new(sgxs));
+	let mut pages=vec![];
+	while let Some((eadd,chunks,_))=try!(reader.read_page()) {
+		let flags=eadd.secinfo.flags;
+		pages.push(PageSummary{
+			offset:eadd.offset,
+			page_type:page_type(flags),
+			flags:flags&!secinfo_flags::PT_MASK,
+			measured:chunks,
+		});
+	}
+	Ok((ecreate,pages))
+}
+
+/// An `SgxsWrite` sink that discards the stream, but counts the pages of
+/// each page type and the number of measured bytes.
+#[derive(Clone,Debug,Default)]
+pub struct CountingSgxsWriter {
+	ecreate: Option<MeasECreate>,
+	pages: HashMap<u8,u64>,
+	measured_bytes: u64,
+}
+
+impl CountingSgxsWriter {
+	pub fn new() -> CountingSgxsWriter {
+		Default::default()
+	}
+
+	/// The last ECREATE written, if any
+	pub fn ecreate(&self) -> Option<&MeasECreate> {
+		self.ecreate.as_ref()
+	}
+
+	pub fn pages(&self, page_type: PageType) -> u64 {
+		self.pages.get(&(page_type as u8)).cloned().unwrap_or(0)
+	}
+
+	pub fn total_pages(&self) -> u64 {
+		self.pages.values().fold(0,|sum,n|sum+n)
+	}
+
+	pub fn measured_bytes(&self) -> u64 {
+		self.measured_bytes
+	}
+
+	fn count_eadd(&mut self, eadd: &MeasEAdd) {
+		*self.pages.entry(eadd.secinfo.flags.page_type()).or_insert(0)+=1;
+	}
+}
+
+impl SgxsWrite for CountingSgxsWriter {
+	fn write_meas(&mut self, meas: &Meas) -> sgxs::Result<()> {
+		match *meas {
+			Meas::ECreate(ref ecreate) => self.ecreate=Some(ecreate.clone()),
+			Meas::EAdd(ref eadd) => self.count_eadd(eadd),
+			Meas::EExtend{..} | Meas::BareEExtend(_) => self.measured_bytes+=256,
+		}
+		Ok(())
+	}
+
+	fn write_page<R: Read>(&mut self, data: MeasuredData<R>, offset: u64, secinfo: SecinfoTruncated) -> sgxs::Result<()> {
+		self.count_eadd(&MeasEAdd{offset:offset,secinfo:secinfo});
+		if let Some(reader)=data {
+			// Consume the page data like a real writer would
+			try!(io::copy(&mut reader.chain(io::repeat(0)).take(4096),&mut io::sink()));
+			self.measured_bytes+=4096;
+		}
+		Ok(())
+	}
+
+	fn write_pages<R: Read>(&mut self, mut data: MeasuredData<R>, n: usize, offset: u64, secinfo: SecinfoTruncated) -> sgxs::Result<()> {
+		for i in 0..(n as u64) {
+			try!(self.write_page(data.as_mut(),offset+4096*i,secinfo.clone()));
+		}
+		Ok(())
+	}
+}
+
+/// The part of a page that differs between two SGXS streams
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PageField {
+	/// Only one of the streams has a page at this index
+	Missing,
+	Offset,
+	PageType,
+	/// Permission flags, without the page type
+	Flags,
+	/// Which 256-byte chunks are measured
+	Measured,
+	Contents,
+}
+
+/// The first difference between two SGXS streams, see `diff_sgxs`
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Diff {
+	/// The enclave size or SSAFRAMESIZE differ
+	ECreate,
+	/// The page at `index` in stream order differs
+	Page{index:usize,field:PageField},
+}
+
+impl fmt::Display for Diff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Diff::ECreate => write!(f,"ECREATE parameters differ"),
+			Diff::Page{index,field} => write!(f,"page {} differs: {:?}",index,field),
+		}
+	}
+}
+
+/// Compare two canonical SGXS streams page by page and return the first
+/// difference, or `None` if they are identical. This explains a changed
+/// MRENCLAVE in terms of the enclave layout.
+pub fn diff_sgxs<R1: SgxsRead, R2: SgxsRead>(mut a: R1, mut b: R2) -> sgxs::Result<Option<Diff>> {
+	let (ecreate_a,mut reader_a)=try!(PageReader::new(&mut a));
+	let (ecreate_b,mut reader_b)=try!(PageReader::new(&mut b));
+	if ecreate_a.size!=ecreate_b.size || ecreate_a.ssaframesize!=ecreate_b.ssaframesize {
+		return Ok(Some(Diff::ECreate));
+	}
+	let mut index=0;
+	loop {
+		let field=match (try!(reader_a.read_page()),try!(reader_b.read_page())) {
+			(None,None) => return Ok(None),
+			(Some(_),None) | (None,Some(_)) => PageField::Missing,
+			(Some((eadd_a,chunks_a,data_a)),Some((eadd_b,chunks_b,data_b))) => {
+				let (flags_a,flags_b)=(eadd_a.secinfo.flags,eadd_b.secinfo.flags);
+				if eadd_a.offset!=eadd_b.offset {
+					PageField::Offset
+				} else if flags_a.page_type()!=flags_b.page_type() {
+					PageField::PageType
+				} else if (flags_a&!secinfo_flags::PT_MASK)!=(flags_b&!secinfo_flags::PT_MASK) {
+					PageField::Flags
+				} else if chunks_a!=chunks_b {
+					PageField::Measured
+				} else if data_a[..]!=data_b[..] {
+					PageField::Contents
+				} else {
+					index+=1;
+					continue;
+				}
+			},
+		};
+		return Ok(Some(Diff::Page{index:index,field:field}));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use sgx_isa::{PageType,SecinfoFlags,secinfo_flags};
+	use sgxs_crate::sgxs::{CanonicalSgxsWriter,MeasECreate,PageChunks,SecinfoTruncated};
+
+	use super::{PageField,Diff,diff_sgxs,summarize};
+
+	/// Four measured pages, the third one with permissions `flags`
+	fn multi_page_sgxs(flags: SecinfoFlags) -> Vec<u8> {
+		let mut buf=vec![];
+		{
+			let mut writer=CanonicalSgxsWriter::new(&mut buf,MeasECreate{size:0x10000,ssaframesize:1}).unwrap();
+			for i in 0..4u8 {
+				let data=[i+1;4096];
+				let flags=if i==2 { flags } else { secinfo_flags::R|secinfo_flags::W };
+				writer.write_page(Some(&mut &data[..]),Some((i as u64)*0x1000),SecinfoTruncated{flags:flags|PageType::Reg.into()}).unwrap();
+			}
+		}
+		buf
+	}
+
+	#[test]
+	fn diff_flags() {
+		let a=multi_page_sgxs(secinfo_flags::R|secinfo_flags::W);
+		let b=multi_page_sgxs(secinfo_flags::R|secinfo_flags::X);
+		assert_eq!(diff_sgxs(&a[..],&b[..]).unwrap(),Some(Diff::Page{index:2,field:PageField::Flags}));
+		assert_eq!(diff_sgxs(&a[..],&a.clone()[..]).unwrap(),None);
+	}
+
+	#[test]
+	fn summarize_pages() {
+		let mut buf=vec![];
+		{
+			let mut writer=CanonicalSgxsWriter::new(&mut buf,MeasECreate{size:0x8000,ssaframesize:2}).unwrap();
+			let data=[0xcc;4096];
+			writer.write_page(Some(&mut &data[..]),Some(0),SecinfoTruncated{flags:PageType::Tcs.into()}).unwrap();
+			writer.write_pages::<&[u8]>(None,2,Some(0x2000),SecinfoTruncated{flags:secinfo_flags::R|secinfo_flags::W|PageType::Reg.into()}).unwrap();
+		}
+
+		let (ecreate,pages)=summarize(&mut &buf[..]).unwrap();
+		assert_eq!(({ecreate.size},{ecreate.ssaframesize}),(0x8000,2));
+		assert_eq!(pages.len(),3);
+		assert_eq!(pages[0].offset,0);
+		assert_eq!(pages[0].page_type,Some(PageType::Tcs));
+		assert_eq!(pages[0].flags,SecinfoFlags::empty());
+		assert_eq!(pages[0].measured,PageChunks(0xffff));
+		for (page,&offset) in pages[1..].iter().zip(&[0x2000,0x3000]) {
+			assert_eq!(page.offset,offset);
+			assert_eq!(page.page_type,Some(PageType::Reg));
+			assert_eq!(page.flags,secinfo_flags::R|secinfo_flags::W);
+			assert_eq!(page.measured,PageChunks(0));
+		}
+		assert_eq!(pages[1].to_string(),"0x00002000 REG  rw- measured=none");
+	}
+}
@@ -9,27 +9,43 @@
  * any later version.
  */
 
-#![feature(float_extras)]
+#![feature(float_extras, custom_derive, plugin)]
+#![cfg_attr(feature="with-serde",plugin(serde_macros))]
 
 #[macro_use]
 extern crate clap;
 extern crate sgxs as sgxs_crate;
 extern crate sgx_isa;
 extern crate xmas_elf;
+#[cfg(feature="with-serde")]
+extern crate serde;
+#[cfg(feature="with-serde")]
+extern crate serde_json;
+#[cfg(feature="log")]
+#[macro_use]
+extern crate log;
 
 mod naming;
 mod num;
 mod elf2sgxs;
 mod exec;
+mod inspect;
+#[cfg(feature="log")]
+mod trace;
 
 use std::path::{Path,PathBuf};
 use std::fs::File;
 use std::ffi::OsStr;
 use std::process::Command;
 use std::io::{Read,Write,Error as IoError};
+use std::mem::transmute;
 
 use xmas_elf::ElfFile;
 
+use sgxs_crate::compress::Compressor;
+use sgxs_crate::sgxs::{SgxsRead,SgxsWrite};
+use sgxs_crate::crypto::{RsaPrivateKeyOps,RsaPrivateKey,Sha256,Sha256Digest};
+
 use clap::ArgMatches;
 
 use exec::{CommandExt,ExecError};
@@ -41,7 +57,20 @@ enum Error {
 	TempFileIo(IoError),
 	LinkExec(ExecError),
 	ElfRead(IoError),
+	OutputWrite(IoError),
 	Elf2Sgxs(elf2sgxs::Error),
+	Summary(sgxs_crate::sgxs::Error),
+	Diff(sgxs_crate::sgxs::Error),
+	Compress(IoError),
+	#[cfg(feature="with-serde")]
+	ManifestSerialize(serde_json::Error),
+	#[cfg(not(feature="with-serde"))]
+	ManifestUnsupported,
+	ManifestWrite(IoError),
+	SigningKeyRead(IoError),
+	SigningKeyParse(<RsaPrivateKey as RsaPrivateKeyOps>::E),
+	Sign(<RsaPrivateKey as RsaPrivateKeyOps>::E),
+	SigstructWrite(IoError),
 }
 
 impl From<elf2sgxs::Error> for Error {
@@ -77,6 +106,43 @@ fn link(srclib: PathBuf, debug: bool) -> Result<PathBuf,Error> {
 	Ok(dstlib)
 }
 
+/// Passes the SGXS stream on to `inner` while hashing it, and keeps a copy
+/// if asked to.
+struct Tee<W: Write> {
+	inner: W,
+	hasher: Sha256,
+	copy: Option<Vec<u8>>,
+}
+
+impl<W: Write> Tee<W> {
+	fn new(inner: W, keep: bool) -> Tee<W> {
+		Tee{inner:inner,hasher:<Sha256 as Sha256Digest>::new(),copy:if keep { Some(vec![]) } else { None }}
+	}
+
+	/// Returns `inner`, the hash of the stream and the copy (empty if not
+	/// kept).
+	fn finish(self) -> (W,[u8;32],Vec<u8>) {
+		let mut hash=[0u8;32];
+		(&mut hash[..]).write_all(&self.hasher.finish()).unwrap();
+		(self.inner,hash,self.copy.unwrap_or_default())
+	}
+}
+
+impl<W: Write> Write for Tee<W> {
+	fn write(&mut self, buf: &[u8]) -> Result<usize,IoError> {
+		let n=try!(self.inner.write(buf));
+		self.hasher.write_all(&buf[..n]).unwrap();
+		if let Some(ref mut copy)=self.copy {
+			copy.extend_from_slice(&buf[..n]);
+		}
+		Ok(n)
+	}
+
+	fn flush(&mut self) -> Result<(),IoError> {
+		self.inner.flush()
+	}
+}
+
 fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>,IoError> {
 	let mut f=try!(File::open(path));
 	let mut buf=vec![];
@@ -84,20 +150,132 @@ fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>,IoError> {
 	Ok(buf)
 }
 
+#[cfg(feature="with-serde")]
+fn write_manifest<P: AsRef<Path>>(manifest: &elf2sgxs::Manifest, path: P) -> Result<(),Error> {
+	let manifest=try!(serde_json::to_string_pretty(manifest).map_err(Error::ManifestSerialize));
+	let mut file=try!(File::create(path).map_err(Error::ManifestWrite));
+	file.write_all(manifest.as_bytes()).map_err(Error::ManifestWrite)
+}
+
+#[cfg(not(feature="with-serde"))]
+fn write_manifest<P: AsRef<Path>>(_manifest: &elf2sgxs::Manifest, _path: P) -> Result<(),Error> {
+	Err(Error::ManifestUnsupported)
+}
+
 fn main_result(args: ArgMatches) -> Result<(),Error> {
-	let ssaframesize=u32::parse_arg(args.value_of("ssaframesize").unwrap());
-	let heap_size=   u64::parse_arg(args.value_of("heap-size")   .unwrap());
-	let stack_size=  u64::parse_arg(args.value_of("stack-size")  .unwrap());
+	let config=elf2sgxs::EnclaveConfig{
+		ssaframesize:args.value_of("ssaframesize").map(u32::parse_arg),
+		heap_size:   args.value_of("heap-size")   .map(u64::parse_arg),
+		heap_addr:   args.value_of("heap-addr")   .map(u64::parse_arg),
+		heap_align:  args.value_of("heap-align")  .map(u64::parse_arg),
+		stack_size:  args.value_of("stack-size")  .map(u64::parse_arg),
+		tls_size:    args.value_of("tls-size")    .map(u64::parse_arg),
+		extra_ssa_frames:args.value_of("extra-ssa-frames").map(u32::parse_arg),
+		tcs_alignment:args.value_of("tcs-alignment").map(u64::parse_arg),
+		tcs_guard_size:args.value_of("tcs-guard-size").map(u64::parse_arg),
+		pad_to_enclave_size:args.value_of("pad").map(|fill|elf2sgxs::PadConfig{
+			fill:u8::parse_arg(fill),
+			measured:args.is_present("pad-measured"),
+		}),
+		extra_heaps:args.values_of("extra-heap").map(|sizes|sizes.map(u64::parse_arg).collect()),
+		reject_debug_info:if args.is_present("no-debug-info") { Some(true) } else { None },
+		enforce_wxorx:if args.is_present("enforce-wxorx") { Some(true) } else { None },
+		debug_trap_at_entry:if args.is_present("debug-trap-at-entry") { Some(true) } else { None },
+		derive_relacount:if args.is_present("derive-relacount") { Some(true) } else { None },
+		miscselect:  args.value_of("miscselect")  .map(u32::parse_arg),
+		xfrm:        args.value_of("xfrm")        .map(u64::parse_arg),
+		..Default::default()
+	};
 	let debug=args.is_present("debug");
+	let strict=args.is_present("strict");
 
 	let srclib=PathBuf::from(args.value_of("staticlib").unwrap());
 	let dstlib=try!(link(srclib,debug));
 	let dstbuf=try!(read_file(&dstlib).map_err(Error::ElfRead));
 	let dstelf=ElfFile::new(&dstbuf);
-	let layout=try!(elf2sgxs::LayoutInfo::new(dstelf,ssaframesize,heap_size,stack_size,debug));
+	let layout=match elf2sgxs::LayoutInfo::new(dstelf,config,debug,strict) {
+		Err(err@elf2sgxs::Error::RelocationInvalidCount{..}) => {
+			print!("{}",elf2sgxs::LayoutInfo::relocation_summary(&ElfFile::new(&dstbuf)));
+			return Err(err.into());
+		},
+		res => try!(res),
+	};
+
+	let compress=args.is_present("compress");
+	let outpath=args.value_of("output").map(PathBuf::from).unwrap_or_else(||dstlib.with_extension(if compress { "sgxs.z" } else { "sgxs" }));
+	// The stream is produced once: written out, hashed, and kept in memory
+	// only if a report below needs to read it again
+	let keep=args.is_present("summary") || args.is_present("diff");
+	let outfile=try!(File::create(&outpath).map_err(Error::OutputWrite));
+	let (mrenclave,stream)=if compress {
+		let mut tee=Tee::new(try!(Compressor::new(outfile).map_err(Error::Compress)),keep);
+		try!(layout.write(&mut tee));
+		let (compressor,mrenclave,stream)=tee.finish();
+		try!(compressor.finish().map_err(Error::Compress));
+		(mrenclave,stream)
+	} else {
+		let mut tee=Tee::new(outfile,keep);
+		try!(layout.write(&mut tee));
+		let (mut outfile,mrenclave,stream)=tee.finish();
+		try!(outfile.flush().map_err(Error::OutputWrite));
+		(mrenclave,stream)
+	};
+
+	if args.is_present("summary") {
+		let (ecreate,pages)=try!(inspect::summarize(&mut &stream[..]).map_err(Error::Summary));
+		println!("ECREATE size=0x{:x} ssaframesize={}",ecreate.size,ecreate.ssaframesize);
+		for page in &pages {
+			println!("EADD {}",page);
+		}
+
+		let mut counter=inspect::CountingSgxsWriter::new();
+		let mut reader=&stream[..];
+		while let Some(meas)=try!(reader.read_meas().map_err(Error::Summary)) {
+			try!(counter.write_meas(&meas).map_err(Error::Summary));
+		}
+		println!("{} pages ({} REG, {} TCS), {} bytes measured",counter.total_pages(),
+			counter.pages(sgx_isa::PageType::Reg),counter.pages(sgx_isa::PageType::Tcs),counter.measured_bytes());
+
+		let mut roles=std::collections::BTreeMap::new();
+		for role in try!(layout.roles(pages.iter().map(|page|(page.offset,page.flags)))) {
+			*roles.entry(format!("{:?}",role)).or_insert(0)+=1;
+		}
+		let roles=roles.iter().map(|(role,n)|format!("{} {}",n,role)).collect::<Vec<_>>();
+		println!("Composition: {}",roles.join(", "));
+	}
+
+	if args.is_present("manifest") {
+		let mut path=outpath.clone().into_os_string();
+		path.push(".json");
+		try!(write_manifest(&try!(layout.manifest(mrenclave)),path));
+	}
 
-	let mut outfile=args.value_of("output").map(|out|File::create(out)).unwrap_or_else(||File::create(dstlib.with_extension("sgxs"))).unwrap();
-	try!(layout.write(&mut outfile));
+	if let Some(path)=args.value_of("diff") {
+		let previous=try!(File::open(path).map_err(|e|Error::Diff(e.into())));
+		match try!(inspect::diff_sgxs(&stream[..],previous).map_err(Error::Diff)) {
+			None => println!("Identical to {}",path),
+			Some(diff) => println!("Differs from {}: {}",path,diff),
+		}
+	}
+
+	if let Some(keypath)=args.value_of("sign") {
+		let mut keyfile=try!(File::open(keypath).map_err(Error::SigningKeyRead));
+		let key=try!(RsaPrivateKey::new(&mut keyfile).map_err(Error::SigningKeyParse));
+		let sig=try!(layout.signer(mrenclave).sign(&key).map_err(Error::Sign));
+		let mut path=outpath.clone().into_os_string();
+		path.push(".sig");
+		let mut file=try!(File::create(path).map_err(Error::SigstructWrite));
+		try!(file.write_all(&unsafe{transmute::<_,[u8;1808]>(sig)}).map_err(Error::SigstructWrite));
+	}
+
+	let padding=try!(layout.padding_pages());
+	if padding>0 {
+		println!("Added {} padding pages up to the enclave size",padding);
+	}
+
+	if let Some((addr,orig))=layout.debug_trap() {
+		println!("Debug trap at entry point 0x{:x}, original byte: 0x{:02x}",addr,orig);
+	}
 
 	Ok(())
 }
@@ -110,9 +288,30 @@ fn main() {
 		.version(crate_version!())
 		.setting(AppSettings::UnifiedHelpMessage)
 		.arg(Arg::with_name("debug").short("d").long("debug").help("Link with the debug runtime"))
-		.arg(Arg::with_name("ssaframesize")         .long("ssaframesize").value_name("PAGES").validator(u32::validate_arg).default_value("1").help("Specify SSAFRAMESIZE"))
-		.arg(Arg::with_name("heap-size") .short("H").long("heap-size")   .value_name("BYTES").validator(u64::validate_arg).required(true)    .help("Specify heap size"))
-		.arg(Arg::with_name("stack-size").short("S").long("stack-size")  .value_name("BYTES").validator(u64::validate_arg).required(true)    .help("Specify stack size"))
+		.arg(Arg::with_name("strict").long("strict").help("Perform additional sanity checks on the linked library"))
+		.arg(Arg::with_name("ssaframesize")         .long("ssaframesize").value_name("PAGES").validator(u32::validate_arg).help("Specify SSAFRAMESIZE (default: 1)"))
+		.arg(Arg::with_name("heap-size") .short("H").long("heap-size")   .value_name("BYTES").validator(u64::validate_arg).help("Specify heap size"))
+		.arg(Arg::with_name("stack-size").short("S").long("stack-size")  .value_name("BYTES").validator(u64::validate_arg).help("Specify stack size"))
+		.arg(Arg::with_name("tls-size")             .long("tls-size").value_name("BYTES").validator(u64::validate_arg).help("Specify the TLS size (default: 0x1000)"))
+		.arg(Arg::with_name("heap-addr")            .long("heap-addr").value_name("ADDR").validator(u64::validate_arg).help("Place the heap at ADDR instead of after the ELF segments"))
+		.arg(Arg::with_name("heap-align")           .long("heap-align").value_name("BYTES").validator(u64::validate_arg).help("Align the heap base to BYTES (default: 0x1000)"))
+		.arg(Arg::with_name("extra-heap")           .long("extra-heap").value_name("BYTES").multiple(true).number_of_values(1).validator(u64::validate_arg).help("Add a heap of BYTES after the primary heap, passed in HEAP<n>_BASE/HEAP<n>_SIZE (n=2,3,...)"))
+		.arg(Arg::with_name("extra-ssa-frames")     .long("extra-ssa-frames").value_name("FRAMES").validator(u32::validate_arg).help("Allocate additional SSA frames not included in NSSA (default: 0)"))
+		.arg(Arg::with_name("tcs-alignment")        .long("tcs-alignment").value_name("BYTES").validator(u64::validate_arg).help("Align the TCS to BYTES (default: 0x1000)"))
+		.arg(Arg::with_name("tcs-guard-size")       .long("tcs-guard-size").value_name("BYTES").validator(u64::validate_arg).help("Leave BYTES of unmapped guard space before the TCS and after the SSA (default: 0)"))
+		.arg(Arg::with_name("miscselect")           .long("miscselect").value_name("BITS").validator(u32::validate_arg).help("Specify MISCSELECT for the SIGSTRUCT (default: 0)"))
+		.arg(Arg::with_name("xfrm")                 .long("xfrm").value_name("BITS").validator(u64::validate_arg).help("Specify ATTRIBUTES.XFRM for the SIGSTRUCT (default: 0x3)"))
+		.arg(Arg::with_name("pad")                  .long("pad").value_name("BYTE").validator(u8::validate_arg).help("Add pages filled with BYTE up to the enclave size"))
+		.arg(Arg::with_name("pad-measured")         .long("pad-measured").requires("pad").help("Measure the padding pages"))
+		.arg(Arg::with_name("no-debug-info")        .long("no-debug-info").help("Reject libraries that contain .debug* sections"))
+		.arg(Arg::with_name("enforce-wxorx")        .long("enforce-wxorx").help("Reject segments that are both writable and executable"))
+		.arg(Arg::with_name("derive-relacount")     .long("derive-relacount").help("Count the relocations if the library has no DT_RELACOUNT entry"))
+		.arg(Arg::with_name("debug-trap-at-entry")  .long("debug-trap-at-entry").requires("debug").help("Patch an int3 instruction over the first byte of the entry point"))
+		.arg(Arg::with_name("compress")             .long("compress").help("Write a compressed SGXS file (default extension: .sgxs.z)"))
+		.arg(Arg::with_name("summary")              .long("summary").help("Print the ECREATE parameters and the pages of the output"))
+		.arg(Arg::with_name("manifest")             .long("manifest").help("Write the build inputs and the enclave layout to <output>.json"))
+		.arg(Arg::with_name("diff")                 .long("diff").value_name("FILE").help("Compare the output with the uncompressed SGXS file FILE and print the first differing page"))
+		.arg(Arg::with_name("sign")                 .long("sign").value_name("KEY").help("Sign the enclave with the PEM-encoded RSA private key KEY and write the SIGSTRUCT to <output>.sig"))
 		.arg(Arg::with_name("output").short("o").long("output").value_name("FILE").help("Specify output file"))
 		.arg(Arg::with_name("staticlib").index(1).required(true).help("Path to the static library to be linked"))
 		.arg(Arg::with_name("agpl-source").long("agpl-source").conflicts_with_all(&["staticlib","heap-size","stack-size"]).help("Print AGPL-licensed files"))
@@ -121,7 +320,11 @@ fn main() {
 	under the GNU Affero General Public License (AGPL). Therefore, distributing
 	object code output by this program requires complying with the AGPL. To see
 	the source code for the AGPL-licensed object code, run this program with
-	--agpl-source.")
+	--agpl-source.
+
+	Settings not specified on the command line are taken from the JSON object
+	in the .sgx_config section of the linked library, if present, and then
+	from the .note.sgxmeta note section, if present.")
 		.get_matches();
 
 	if args.is_present("agpl-source") {
@@ -129,6 +332,9 @@ fn main() {
 		return;
 	}
 
+	#[cfg(feature="log")]
+	trace::init();
+
 	if let Err(e)=main_result(args) {
 		println!("Error: {:?}",e);
 		std::process::exit(1);
@@ -36,6 +36,12 @@ fn parse_num<T: NumArg, S: Borrow<str>>(s: S) -> Result<T,ParseIntError> {
 	}
 }
 
+impl NumArg for u8 {
+	fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError> {
+		Self::from_str_radix(src,radix)
+	}
+}
+
 impl NumArg for u32 {
 	fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError> {
 		Self::from_str_radix(src,radix)
@@ -0,0 +1,38 @@
+/*
+ * Tools for building and linking enclaves using libenclave.
+ *
+ * (C) Copyright 2016 Jethro G. Beekman
+ *
+ * This program is free software; you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation; either version 2 of the License, or (at your option)
+ * any later version.
+ */
+
+//! Step-by-step trace of the ELF to SGXS conversion on stderr. Only built
+//! with the `log` feature.
+
+use std::io::{stderr,Write};
+
+use log::{self,Log,LogRecord,LogMetadata,LogLevel,LogLevelFilter};
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+	fn enabled(&self, metadata: &LogMetadata) -> bool {
+		metadata.level()<=LogLevel::Debug
+	}
+
+	fn log(&self, record: &LogRecord) {
+		if self.enabled(record.metadata()) {
+			let _=writeln!(stderr(),"{}: {}",record.level(),record.args());
+		}
+	}
+}
+
+pub fn init() {
+	let _=log::set_logger(|max_level|{
+		max_level.set(LogLevelFilter::Debug);
+		Box::new(StderrLogger)
+	});
+}
@@ -18,6 +18,12 @@ use spin::Mutex;
 use alloc_buddy_simple::{FreeBlock,Heap};
 use mem;
 
+/// Tells the enclave linker that the heap is managed by a buddy allocator, so
+/// the heap size must be a power of two.
+#[cfg(feature="allocator")]
+#[no_mangle]
+pub static ALLOCATOR_KIND: u64 = 1;
+
 #[cfg(feature="allocator")]
 pub fn init() {
 	use alloc_buddy_simple::initialize_allocator;
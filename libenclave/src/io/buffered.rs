@@ -141,6 +141,58 @@ impl<R: Read> BufReader<R> {
     /// ```
     pub fn get_mut(&mut self) -> &mut R { &mut self.inner }
 
+    /// Returns the next `n` bytes without consuming them.
+    ///
+    /// Reads from the underlying reader until at least `n` bytes are
+    /// buffered. Unread bytes are first moved to the start of the buffer to
+    /// make room, so they stay valid for later reads. The returned slice is
+    /// shorter than `n` only if EOF was reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of the kind `ErrorKind::InvalidInput` if `n` is
+    /// larger than the capacity of the buffer. Errors of the underlying
+    /// reader other than `ErrorKind::Interrupted` are returned as well, in
+    /// which case the bytes buffered so far are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::prelude::*;
+    /// use std::io::BufReader;
+    ///
+    /// let mut reader = BufReader::new(&b"GET /"[..]);
+    /// assert_eq!(reader.peek(3).unwrap(), b"GET");
+    /// let mut s = String::new();
+    /// reader.read_to_string(&mut s).unwrap();
+    /// assert_eq!(s, "GET /");
+    /// ```
+    pub fn peek(&mut self, n: usize) -> io::Result<&[u8]> {
+        if n > self.buf.len() {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  "peek is larger than the buffer capacity"));
+        }
+        if self.cap - self.pos < n {
+            if self.pos > 0 {
+                let len = self.cap - self.pos;
+                for i in 0..len {
+                    self.buf[i] = self.buf[self.pos + i];
+                }
+                self.pos = 0;
+                self.cap = len;
+            }
+            while self.cap < n {
+                match self.inner.read(&mut self.buf[self.cap..]) {
+                    Ok(0) => break,
+                    Ok(len) => self.cap += len,
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(&self.buf[self.pos..cmp::min(self.pos + n, self.cap)])
+    }
+
     /// Unwraps this `BufReader`, returning the underlying reader.
     ///
     /// Note that any leftover data in the internal buffer is lost.
@@ -774,6 +826,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_buffered_reader_peek() {
+        let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4];
+        let mut reader = BufReader::with_capacity(4, io::SlowReader::new(inner, 1));
+
+        assert_eq!(reader.peek(3).unwrap(), &[5u8, 6, 7]);
+        assert_eq!(reader.peek(2).unwrap(), &[5u8, 6]);
+        let mut buf = [0, 0];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(buf, [5, 6]);
+
+        // Moves the unread byte to the front to make room
+        assert_eq!(reader.peek(4).unwrap(), &[7u8, 0, 1, 2]);
+        assert_eq!(reader.peek(5).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let mut buf = [0; 3];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [7, 0, 1]);
+
+        // Short at EOF
+        assert_eq!(reader.peek(4).unwrap(), &[2u8, 3, 4]);
+        let mut v = Vec::new();
+        reader.read_to_end(&mut v).unwrap();
+        assert_eq!(v, [2, 3, 4]);
+        assert!(reader.peek(1).unwrap().is_empty());
+    }
+
     #[test]
     fn test_buffered_reader() {
         let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4];
@@ -872,6 +950,22 @@ mod tests {
         assert_eq!(reader.get_ref().pos, expected);
     }
 
+    /// A dummy writer intended at testing short-writes handling.
+    pub struct ShortWriter {
+        written: Vec<u8>,
+        lengths: Vec<usize>,
+    }
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let len = if self.lengths.is_empty() { buf.len() } else { self.lengths.remove(0) };
+            let len = ::core::cmp::min(len, buf.len());
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
     #[test]
     fn test_buffered_writer() {
         let inner = Vec::new();
@@ -915,6 +1009,24 @@ mod tests {
         assert_eq!(w, [0, 1]);
     }
 
+    #[test]
+    fn test_buffered_writer_short_writes() {
+        let inner = ShortWriter { written: Vec::new(), lengths: vec![1, 2, 1, 3] };
+        let mut w = BufWriter::with_capacity(4, inner);
+        w.write_all(&[0, 1, 2]).unwrap();
+        w.write_all(&[3, 4, 5, 6, 7]).unwrap();
+        w.write_all(&[8, 9]).unwrap();
+        let inner = w.into_inner().unwrap();
+        assert_eq!(inner.written, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let inner = ShortWriter { written: Vec::new(), lengths: vec![1, 1, 1] };
+        let mut w = LineWriter::new(inner);
+        w.write_all(b"ab\ncd").unwrap();
+        assert_eq!(w.get_ref().written, b"ab\n");
+        let inner = w.into_inner().unwrap();
+        assert_eq!(inner.written, b"ab\ncd");
+    }
+
     #[test]
     fn test_buffered_writer_seek() {
         let mut w = BufWriter::with_capacity(3, io::Cursor::new(Vec::new()));
@@ -0,0 +1,184 @@
+/*
+ * The Rust secure enclave runtime and library.
+ *
+ * (C) Copyright 2016 Jethro G. Beekman
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ */
+
+//! Reading and writing integers in a fixed byte order.
+
+use io::{Read, Write, Result};
+
+fn from_le(buf: &[u8]) -> u64 {
+    buf.iter().rev().fold(0, |v, &b| (v << 8) | b as u64)
+}
+
+fn from_be(buf: &[u8]) -> u64 {
+    buf.iter().fold(0, |v, &b| (v << 8) | b as u64)
+}
+
+fn to_le(n: u64, buf: &mut [u8]) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = (n >> (8 * i)) as u8;
+    }
+}
+
+fn to_be(n: u64, buf: &mut [u8]) {
+    for (i, b) in buf.iter_mut().rev().enumerate() {
+        *b = (n >> (8 * i)) as u8;
+    }
+}
+
+/// Extension methods for reading little- and big-endian integers.
+///
+/// Each method reads exactly the size of the integer using `read_exact`, so
+/// the errors are those of `read_exact`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::ReadBytesExt;
+///
+/// let mut reader = &[0x34u8, 0x12, 0x12, 0x34][..];
+/// assert_eq!(reader.read_u16_le().unwrap(), 0x1234);
+/// assert_eq!(reader.read_u16_be().unwrap(), 0x1234);
+/// ```
+pub trait ReadBytesExt: Read {
+    fn read_u16_le(&mut self) -> Result<u16> {
+        let mut buf = [0; 2];
+        try!(self.read_exact(&mut buf));
+        Ok(from_le(&buf) as u16)
+    }
+
+    fn read_u32_le(&mut self) -> Result<u32> {
+        let mut buf = [0; 4];
+        try!(self.read_exact(&mut buf));
+        Ok(from_le(&buf) as u32)
+    }
+
+    fn read_u64_le(&mut self) -> Result<u64> {
+        let mut buf = [0; 8];
+        try!(self.read_exact(&mut buf));
+        Ok(from_le(&buf))
+    }
+
+    fn read_u16_be(&mut self) -> Result<u16> {
+        let mut buf = [0; 2];
+        try!(self.read_exact(&mut buf));
+        Ok(from_be(&buf) as u16)
+    }
+
+    fn read_u32_be(&mut self) -> Result<u32> {
+        let mut buf = [0; 4];
+        try!(self.read_exact(&mut buf));
+        Ok(from_be(&buf) as u32)
+    }
+
+    fn read_u64_be(&mut self) -> Result<u64> {
+        let mut buf = [0; 8];
+        try!(self.read_exact(&mut buf));
+        Ok(from_be(&buf))
+    }
+}
+
+impl<R: Read + ?Sized> ReadBytesExt for R {}
+
+/// Extension methods for writing little- and big-endian integers.
+///
+/// Each method writes the whole integer using `write_all`, so the errors are
+/// those of `write_all`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::WriteBytesExt;
+///
+/// let mut buf = Vec::new();
+/// buf.write_u16_le(0x1234).unwrap();
+/// buf.write_u16_be(0x1234).unwrap();
+/// assert_eq!(buf, [0x34u8, 0x12, 0x12, 0x34]);
+/// ```
+pub trait WriteBytesExt: Write {
+    fn write_u16_le(&mut self, n: u16) -> Result<()> {
+        let mut buf = [0; 2];
+        to_le(n as u64, &mut buf);
+        self.write_all(&buf)
+    }
+
+    fn write_u32_le(&mut self, n: u32) -> Result<()> {
+        let mut buf = [0; 4];
+        to_le(n as u64, &mut buf);
+        self.write_all(&buf)
+    }
+
+    fn write_u64_le(&mut self, n: u64) -> Result<()> {
+        let mut buf = [0; 8];
+        to_le(n, &mut buf);
+        self.write_all(&buf)
+    }
+
+    fn write_u16_be(&mut self, n: u16) -> Result<()> {
+        let mut buf = [0; 2];
+        to_be(n as u64, &mut buf);
+        self.write_all(&buf)
+    }
+
+    fn write_u32_be(&mut self, n: u32) -> Result<()> {
+        let mut buf = [0; 4];
+        to_be(n as u64, &mut buf);
+        self.write_all(&buf)
+    }
+
+    fn write_u64_be(&mut self, n: u64) -> Result<()> {
+        let mut buf = [0; 8];
+        to_be(n, &mut buf);
+        self.write_all(&buf)
+    }
+}
+
+impl<W: Write + ?Sized> WriteBytesExt for W {}
+
+#[cfg(test)]
+mod tests {
+    use prelude::v1::*;
+
+    use io::{ErrorKind, ReadBytesExt, WriteBytesExt};
+
+    #[test]
+    fn round_trip() {
+        let mut buf = Vec::new();
+        buf.write_u16_le(0x0102).unwrap();
+        buf.write_u32_le(0x03040506).unwrap();
+        buf.write_u64_le(0x0708090a0b0c0d0e).unwrap();
+        buf.write_u16_be(0x0102).unwrap();
+        buf.write_u32_be(0x03040506).unwrap();
+        buf.write_u64_be(0x0708090a0b0c0d0e).unwrap();
+        assert_eq!(&buf[..14], &[0x02u8, 0x01, 0x06, 0x05, 0x04, 0x03,
+                                 0x0e, 0x0d, 0x0c, 0x0b, 0x0a, 0x09, 0x08, 0x07]);
+        assert_eq!(&buf[14..], &[0x01u8, 0x02, 0x03, 0x04, 0x05, 0x06,
+                                 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e]);
+
+        let mut r = &buf[..];
+        assert_eq!(r.read_u16_le().unwrap(), 0x0102);
+        assert_eq!(r.read_u32_le().unwrap(), 0x03040506);
+        assert_eq!(r.read_u64_le().unwrap(), 0x0708090a0b0c0d0e);
+        assert_eq!(r.read_u16_be().unwrap(), 0x0102);
+        assert_eq!(r.read_u32_be().unwrap(), 0x03040506);
+        assert_eq!(r.read_u64_be().unwrap(), 0x0708090a0b0c0d0e);
+        assert_eq!(r.read_u16_le().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn extremes() {
+        let mut buf = Vec::new();
+        buf.write_u64_le(!0).unwrap();
+        buf.write_u64_be(1 << 63).unwrap();
+        let mut r = &buf[..];
+        assert_eq!(r.read_u64_le().unwrap(), !0);
+        assert_eq!(r.read_u64_be().unwrap(), 1 << 63);
+    }
+}
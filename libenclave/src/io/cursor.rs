@@ -529,6 +529,27 @@ mod tests {
         assert!(r.seek(SeekFrom::End(-2)).is_err());
     }
 
+    #[test]
+    fn stream_position_and_len() {
+        let mut r = Cursor::new(vec![1u8, 2, 3, 4, 5]);
+        assert_eq!(r.stream_position().unwrap(), 0);
+        assert_eq!(r.stream_len().unwrap(), 5);
+        assert_eq!(r.stream_position().unwrap(), 0);
+
+        r.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(r.stream_position().unwrap(), 2);
+        assert_eq!(r.stream_position().unwrap(), 2);
+        assert_eq!(r.stream_len().unwrap(), 5);
+        assert_eq!(r.position(), 2);
+        let mut buf = [0];
+        r.read(&mut buf).unwrap();
+        assert_eq!(buf, [3u8]);
+
+        r.seek(SeekFrom::Start(10)).unwrap();
+        assert_eq!(r.stream_len().unwrap(), 5);
+        assert_eq!(r.stream_position().unwrap(), 10);
+    }
+
     #[test]
     fn test_seekable_mem_writer() {
         let mut writer = Cursor::new(Vec::<u8>::new());
@@ -67,7 +67,7 @@ pub struct Error {
 }
 
 enum Repr {
-    Os,
+    Os(i32),
     Custom(Box<Custom>),
 }
 
@@ -144,6 +144,11 @@ pub enum ErrorKind {
     /// read.
     UnexpectedEof,
 
+    /// The operation was canceled, for example through a `Cancelable`.
+    ///
+    /// Unlike `Interrupted`, this is not retried.
+    Canceled,
+
     /// Any I/O error not part of this list.
     #[doc(hidden)]
     __Nonexhaustive,
@@ -185,16 +190,29 @@ impl Error {
 
     /// Returns an error representing the last OS error which occurred.
     ///
-    /// This function reads the value of `errno` for the target platform (e.g.
-    /// `GetLastError` on Windows) and will return a corresponding instance of
-    /// `Error` for the error code.
+    /// There is no `errno` inside the enclave, so this returns an OS error
+    /// with code 0. Use `from_raw_os_error` with the error number returned by
+    /// a usercall instead.
     pub fn last_os_error() -> Error {
-        Error { repr: Repr::Os }
+        Error::from_raw_os_error(0)
     }
 
     /// Creates a new instance of an `Error` from a particular OS error code.
-    pub fn from_raw_os_error() -> Error {
-        Error { repr: Repr::Os }
+    ///
+    /// The code is an error number of the host, as returned by usercalls.
+    /// The `ErrorKind` is derived from it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// let error = io::Error::from_raw_os_error(104);
+    /// assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+    /// assert_eq!(error.raw_os_error(), Some(104));
+    /// ```
+    pub fn from_raw_os_error(code: i32) -> Error {
+        Error { repr: Repr::Os(code) }
     }
 
     /// Returns the OS error that this error represents (if any).
@@ -202,9 +220,9 @@ impl Error {
     /// If this `Error` was constructed via `last_os_error` or
     /// `from_raw_os_error`, then this function will return `Some`, otherwise
     /// it will return `None`.
-    pub fn raw_os_error(&self) -> Option<()> {
+    pub fn raw_os_error(&self) -> Option<i32> {
         match self.repr {
-            Repr::Os => Some(()),
+            Repr::Os(code) => Some(code),
             Repr::Custom(..) => None,
         }
     }
@@ -215,7 +233,7 @@ impl Error {
     /// return `Some`, otherwise it will return `None`.
     pub fn get_ref(&self) -> Option<&String> {
         match self.repr {
-            Repr::Os => None,
+            Repr::Os(..) => None,
             Repr::Custom(ref c) => Some(&c.error),
         }
     }
@@ -227,7 +245,7 @@ impl Error {
     /// return `Some`, otherwise it will return `None`.
     pub fn get_mut(&mut self) -> Option<&mut String> {
         match self.repr {
-            Repr::Os => None,
+            Repr::Os(..) => None,
             Repr::Custom(ref mut c) => Some(&mut c.error),
         }
     }
@@ -238,7 +256,7 @@ impl Error {
     /// return `Some`, otherwise it will return `None`.
     pub fn into_inner(self) -> Option<String> {
         match self.repr {
-            Repr::Os => None,
+            Repr::Os(..) => None,
             Repr::Custom(c) => Some(c.error)
         }
     }
@@ -246,7 +264,7 @@ impl Error {
     /// Returns the corresponding `ErrorKind` for this error.
     pub fn kind(&self) -> ErrorKind {
         match self.repr {
-            Repr::Os => ErrorKind::Other,
+            Repr::Os(code) => decode_error_kind(code),
             Repr::Custom(ref c) => c.kind,
         }
     }
@@ -255,12 +273,42 @@ impl Error {
 impl fmt::Debug for Repr {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Repr::Os => fmt.debug_struct("Os").finish(),
+            Repr::Os(code) => fmt.debug_struct("Os").field("code", &code).finish(),
             Repr::Custom(ref c) => fmt.debug_tuple("Custom").field(c).finish(),
         }
     }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.repr {
+            Repr::Os(code) => write!(fmt, "os error {}", code),
+            Repr::Custom(ref c) => write!(fmt, "{:?}: {}", c.kind, c.error),
+        }
+    }
+}
+
+// Error numbers of the (Linux) host
+fn decode_error_kind(code: i32) -> ErrorKind {
+    match code {
+        1 | 13 => ErrorKind::PermissionDenied, // EPERM, EACCES
+        2 => ErrorKind::NotFound,              // ENOENT
+        4 => ErrorKind::Interrupted,           // EINTR
+        11 => ErrorKind::WouldBlock,           // EAGAIN
+        17 => ErrorKind::AlreadyExists,        // EEXIST
+        22 => ErrorKind::InvalidInput,         // EINVAL
+        32 => ErrorKind::BrokenPipe,           // EPIPE
+        98 => ErrorKind::AddrInUse,            // EADDRINUSE
+        99 => ErrorKind::AddrNotAvailable,     // EADDRNOTAVAIL
+        103 => ErrorKind::ConnectionAborted,   // ECONNABORTED
+        104 => ErrorKind::ConnectionReset,     // ECONNRESET
+        107 => ErrorKind::NotConnected,        // ENOTCONN
+        110 => ErrorKind::TimedOut,            // ETIMEDOUT
+        111 => ErrorKind::ConnectionRefused,   // ECONNREFUSED
+        _ => ErrorKind::Other,
+    }
+}
+
 fn _assert_error_is_sync_send() {
     fn _is_sync_send<T: Sync+Send>() {}
     _is_sync_send::<Error>();
@@ -284,6 +332,27 @@ mod test {
         assert_eq!(format!("{:?}", err), expected);
     }
 
+    #[test]
+    fn test_raw_os_error() {
+        let err = Error::from_raw_os_error(104);
+        assert_eq!(err.raw_os_error(), Some(104));
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert_eq!(Error::from_raw_os_error(32).kind(), ErrorKind::BrokenPipe);
+        assert_eq!(Error::from_raw_os_error(12345).kind(), ErrorKind::Other);
+        assert_eq!(format!("{}", err), "os error 104");
+        assert!(err.get_ref().is_none());
+        assert_eq!(Error::new(ErrorKind::Other, "x").raw_os_error(), None);
+    }
+
+    #[test]
+    fn test_display_error() {
+        let err = Error::new(ErrorKind::InvalidInput, "bad offset");
+        assert_eq!(format!("{}", err), "InvalidInput: bad offset");
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(err.get_ref().map(|s| &s[..]), Some("bad offset"));
+        assert_eq!(err.into_inner().unwrap(), "bad offset");
+    }
+
     #[test]
     fn test_downcasting() {
         #[derive(Debug)]
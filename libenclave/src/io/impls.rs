@@ -59,6 +59,16 @@ impl<'a, W: Write + ?Sized> Write for &'a mut W {
         (**self).write_all(buf)
     }
 
+    #[inline]
+    fn write_all_retry(&mut self, buf: &[u8], max_retries: usize) -> io::Result<()> {
+        (**self).write_all_retry(buf, max_retries)
+    }
+
+    #[inline]
+    fn write_all_vectored(&mut self, bufs: &[&[u8]]) -> io::Result<()> {
+        (**self).write_all_vectored(bufs)
+    }
+
     #[inline]
     fn write_fmt(&mut self, fmt: fmt::Arguments) -> io::Result<()> {
         (**self).write_fmt(fmt)
@@ -67,6 +77,12 @@ impl<'a, W: Write + ?Sized> Write for &'a mut W {
 impl<'a, S: Seek + ?Sized> Seek for &'a mut S {
     #[inline]
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> { (**self).seek(pos) }
+
+    #[inline]
+    fn stream_position(&mut self) -> io::Result<u64> { (**self).stream_position() }
+
+    #[inline]
+    fn stream_len(&mut self) -> io::Result<u64> { (**self).stream_len() }
 }
 impl<'a, B: BufRead + ?Sized> BufRead for &'a mut B {
     #[inline]
@@ -80,6 +96,11 @@ impl<'a, B: BufRead + ?Sized> BufRead for &'a mut B {
         (**self).read_until(byte, buf)
     }
 
+    #[inline]
+    fn read_until_strict(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        (**self).read_until_strict(byte, buf)
+    }
+
     #[inline]
     fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
         (**self).read_line(buf)
@@ -119,6 +140,16 @@ impl<W: Write + ?Sized> Write for Box<W> {
         (**self).write_all(buf)
     }
 
+    #[inline]
+    fn write_all_retry(&mut self, buf: &[u8], max_retries: usize) -> io::Result<()> {
+        (**self).write_all_retry(buf, max_retries)
+    }
+
+    #[inline]
+    fn write_all_vectored(&mut self, bufs: &[&[u8]]) -> io::Result<()> {
+        (**self).write_all_vectored(bufs)
+    }
+
     #[inline]
     fn write_fmt(&mut self, fmt: fmt::Arguments) -> io::Result<()> {
         (**self).write_fmt(fmt)
@@ -127,6 +158,12 @@ impl<W: Write + ?Sized> Write for Box<W> {
 impl<S: Seek + ?Sized> Seek for Box<S> {
     #[inline]
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> { (**self).seek(pos) }
+
+    #[inline]
+    fn stream_position(&mut self) -> io::Result<u64> { (**self).stream_position() }
+
+    #[inline]
+    fn stream_len(&mut self) -> io::Result<u64> { (**self).stream_len() }
 }
 impl<B: BufRead + ?Sized> BufRead for Box<B> {
     #[inline]
@@ -140,6 +177,11 @@ impl<B: BufRead + ?Sized> BufRead for Box<B> {
         (**self).read_until(byte, buf)
     }
 
+    #[inline]
+    fn read_until_strict(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        (**self).read_until_strict(byte, buf)
+    }
+
     #[inline]
     fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
         (**self).read_line(buf)
@@ -149,6 +191,9 @@ impl<B: BufRead + ?Sized> BufRead for Box<B> {
 // =============================================================================
 // In-memory buffer implementations
 
+// There is no `Read` impl for `&[u8; N]`: a reference to an array can't be
+// advanced. To read from an array, read from a slice of it (`&arr[..]`), which
+// advances as data is read, or wrap an owned array in a `Cursor`.
 impl<'a> Read for &'a [u8] {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -222,10 +267,95 @@ impl Write for Vec<u8> {
 
 #[cfg(test)]
 mod tests {
+    use io;
     use io::prelude::*;
     use vec::Vec;
     use test;
 
+    #[test]
+    fn read_from_array() {
+        let arr = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let mut buf = [0u8; 4];
+
+        let mut rd = &arr[..];
+        assert_eq!(rd.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [1, 2, 3, 4]);
+        assert_eq!(rd.len(), 12);
+
+        let mut rd = io::Cursor::new(arr);
+        assert_eq!(rd.read(&mut buf).unwrap(), 4);
+        assert_eq!(rd.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [5, 6, 7, 8]);
+        assert_eq!(rd.position(), 8);
+    }
+
+    #[test]
+    fn write_through_mut_vec() {
+        fn write_hello<W: Write>(mut w: W) {
+            w.write_all(b"hello").unwrap();
+        }
+
+        let mut buf = Vec::new();
+        write_hello(&mut buf);
+        write_hello(&mut buf);
+        assert_eq!(buf, b"hellohello");
+    }
+
+    // Benchmark baseline only, not used outside this module: the
+    // `read_to_end` implementation before the buffer grew geometrically, to
+    // compare `bench_read_to_end_1mib` against
+    fn read_to_end_fixed_growth<R: Read>(r: &mut R, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start_len = buf.len();
+        let mut len = start_len;
+        let mut new_write_size = 16;
+        let ret;
+        loop {
+            if len == buf.len() {
+                if new_write_size < io::DEFAULT_BUF_SIZE {
+                    new_write_size *= 2;
+                }
+                buf.resize(len + new_write_size, 0);
+            }
+
+            match r.read(&mut buf[len..]) {
+                Ok(0) => {
+                    ret = Ok(len - start_len);
+                    break;
+                }
+                Ok(n) => len += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    ret = Err(e);
+                    break;
+                }
+            }
+        }
+
+        buf.truncate(len);
+        ret
+    }
+
+    #[bench]
+    fn bench_read_to_end_1mib(b: &mut test::Bencher) {
+        b.iter(|| {
+            // `Take` uses the default `read_to_end` implementation
+            let mut rd = io::repeat(5).take(1 << 20);
+            let mut dst = Vec::new();
+            rd.read_to_end(&mut dst).unwrap();
+            test::black_box(&dst);
+        })
+    }
+
+    #[bench]
+    fn bench_read_to_end_1mib_fixed_growth(b: &mut test::Bencher) {
+        b.iter(|| {
+            let mut rd = io::repeat(5).take(1 << 20);
+            let mut dst = Vec::new();
+            read_to_end_fixed_growth(&mut rd, &mut dst).unwrap();
+            test::black_box(&dst);
+        })
+    }
+
     #[bench]
     fn bench_read_slice(b: &mut test::Bencher) {
         let buf = [5; 1024];
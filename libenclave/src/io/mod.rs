@@ -264,21 +264,29 @@ use core::ops::{Drop, FnOnce};
 use core::option::Option::{self, Some, None};
 use core::result::Result::{Ok, Err};
 use core::result;
+use core::ptr;
 use collections::string::String;
 use core::str;
 use collections::vec::Vec;
 
 pub use self::buffered::{BufReader, BufWriter, LineWriter};
 pub use self::buffered::IntoInnerError;
+pub use self::byteorder::{ReadBytesExt, WriteBytesExt};
 pub use self::cursor::Cursor;
 pub use self::error::{Result, Error, ErrorKind};
-pub use self::util::{copy, sink, Sink, empty, Empty, repeat, Repeat};
+pub use self::ring::RingBuffer;
+pub use self::stdio::{stdin, stdout, stderr, Stdin, Stdout, Stderr};
+pub use self::stdio::{StdinLock, StdoutLock, StderrLock};
+pub use self::util::{copy, copy_buf, copy_bounded, copy_with, copy_with_retries, read_buf_fully, read_to_string_strict, sink, Sink, empty, Empty, repeat, Repeat, repeat_pattern, RepeatPattern, SlowReader, CountWriter, LimitWriter, Cancelable, Digest, HashingReader, HashingWriter, ZeroizeBuf, read_secret};
 
 pub mod prelude;
 mod buffered;
+mod byteorder;
 mod cursor;
 mod error;
 mod impls;
+mod ring;
+mod stdio;
 mod util;
 mod memchr;
 
@@ -333,25 +341,61 @@ fn append_to_string<F>(buf: &mut String, f: F) -> Result<usize>
 // of data to return. Simply tacking on an extra DEFAULT_BUF_SIZE space every
 // time is 4,500 times (!) slower than this if the reader has a very small
 // amount of data to return.
+//
+// The capacity of the vector grows geometrically, and the spare capacity is
+// read into directly. The spare capacity is zeroed first since reading into
+// uninitialized memory is unsound for arbitrary readers. If `read` panics,
+// the guard makes sure the length only covers the data actually read.
+//
+// Once the vector is large, a full vector is probed with a small read before
+// doubling it. If the reader is at EOF, which is likely if its size matches
+// the capacity, this avoids allocating and zeroing memory that is never used.
 fn read_to_end<R: Read + ?Sized>(r: &mut R, buf: &mut Vec<u8>) -> Result<usize> {
+    struct Guard<'a> { buf: &'a mut Vec<u8>, len: usize }
+
+    impl<'a> Drop for Guard<'a> {
+        fn drop(&mut self) {
+            unsafe { self.buf.set_len(self.len); }
+        }
+    }
+
     let start_len = buf.len();
-    let mut len = start_len;
-    let mut new_write_size = 16;
+    let mut g = Guard { len: buf.len(), buf: buf };
     let ret;
     loop {
-        if len == buf.len() {
-            if new_write_size < DEFAULT_BUF_SIZE {
-                new_write_size *= 2;
+        if g.len == g.buf.len() {
+            if g.len == g.buf.capacity() && g.len >= DEFAULT_BUF_SIZE {
+                let mut probe = [0u8; 32];
+                match r.read(&mut probe) {
+                    Ok(0) => {
+                        ret = Ok(g.len - start_len);
+                        break;
+                    }
+                    Ok(n) => {
+                        g.buf.extend_from_slice(&probe[..n]);
+                        g.len += n;
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        ret = Err(e);
+                        break;
+                    }
+                }
+            }
+            unsafe {
+                g.buf.reserve(32);
+                let capacity = g.buf.capacity();
+                g.buf.set_len(capacity);
+                ptr::write_bytes(g.buf.as_mut_ptr().offset(g.len as isize), 0, capacity - g.len);
             }
-            buf.resize(len + new_write_size, 0);
         }
 
-        match r.read(&mut buf[len..]) {
+        match r.read(&mut g.buf[g.len..]) {
             Ok(0) => {
-                ret = Ok(len - start_len);
+                ret = Ok(g.len - start_len);
                 break;
             }
-            Ok(n) => len += n,
+            Ok(n) => g.len += n,
             Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
             Err(e) => {
                 ret = Err(e);
@@ -360,7 +404,6 @@ fn read_to_end<R: Read + ?Sized>(r: &mut R, buf: &mut Vec<u8>) -> Result<usize>
         }
     }
 
-    buf.truncate(len);
     ret
 }
 
@@ -803,7 +846,6 @@ pub trait Read {
     /// [file]: ../fs/struct.File.html
     ///
     /// ```
-    /// #![feature(io)]
     /// use std::io;
     /// use std::io::prelude::*;
     /// use std::fs::File;
@@ -820,7 +862,6 @@ pub trait Read {
     /// # Ok(())
     /// # }
     /// ```
-    #[allow(deprecated)]
     fn tee<W: Write>(self, out: W) -> Tee<Self, W> where Self: Sized {
         Tee { reader: self, writer: out }
     }
@@ -959,6 +1000,74 @@ pub trait Write {
         Ok(())
     }
 
+    /// Attempts to write an entire buffer into this write, retrying writes
+    /// that write 0 bytes.
+    ///
+    /// `write_all` treats a write of 0 bytes as fatal, which is right for
+    /// sinks that are full for good, such as `&mut [u8]`. Writers backed by
+    /// usercalls may also return 0 while they are only transiently full, for
+    /// example when writing to a full pipe. This method retries up to
+    /// `max_retries` consecutive writes of 0 bytes, and resets the count
+    /// whenever some data is written.
+    ///
+    /// There is no way to yield the thread from inside the enclave, so the
+    /// retries are not delayed. Every write of a usercall-backed writer
+    /// leaves the enclave, which gives the host a chance to make room.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of the kind `ErrorKind::WriteZero` if more than
+    /// `max_retries` consecutive writes wrote 0 bytes. Other errors are those
+    /// of `write_all`.
+    fn write_all_retry(&mut self, mut buf: &[u8], max_retries: usize) -> Result<()> {
+        let mut retries = 0;
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) if retries < max_retries => retries += 1,
+                Ok(0) => return Err(Error::new(ErrorKind::WriteZero,
+                                               "failed to write whole buffer")),
+                Ok(n) => {
+                    buf = &buf[n..];
+                    retries = 0;
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to write several buffers into this writer, in order.
+    ///
+    /// The default implementation calls `write_all` for each buffer.
+    /// Writers for which each write is expensive, for example because it
+    /// requires a usercall, can override this to write all buffers at once.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error that `write_all` returns.
+    /// Buffers before the failing one have been written completely.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::prelude::*;
+    ///
+    /// # fn foo() -> std::io::Result<()> {
+    /// let mut buffer = Vec::new();
+    ///
+    /// try!(buffer.write_all_vectored(&[b"header", b"body"]));
+    /// assert_eq!(buffer, b"headerbody");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn write_all_vectored(&mut self, bufs: &[&[u8]]) -> Result<()> {
+        for buf in bufs {
+            try!(self.write_all(buf));
+        }
+        Ok(())
+    }
+
     /// Writes a formatted string into this writer, returning any error
     /// encountered.
     ///
@@ -1059,7 +1168,6 @@ pub trait Write {
     /// # Examples
     ///
     /// ```
-    /// #![feature(io)]
     /// use std::io::prelude::*;
     /// use std::fs::File;
     ///
@@ -1074,7 +1182,6 @@ pub trait Write {
     /// # Ok(())
     /// # }
     /// ```
-    #[allow(deprecated)]
     fn broadcast<W: Write>(self, other: W) -> Broadcast<Self, W>
         where Self: Sized
     {
@@ -1122,6 +1229,45 @@ pub trait Seek {
     ///
     /// Seeking to a negative offset is considered an error.
     fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+    /// Returns the current position from the start of the stream.
+    ///
+    /// This is equivalent to `self.seek(SeekFrom::Current(0))` and does not
+    /// change the position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::prelude::*;
+    /// use std::io::{Cursor, SeekFrom};
+    ///
+    /// let mut c = Cursor::new(vec![1, 2, 3, 4]);
+    /// c.seek(SeekFrom::Start(1)).unwrap();
+    /// assert_eq!(c.stream_position().unwrap(), 1);
+    /// assert_eq!(c.stream_len().unwrap(), 4);
+    /// assert_eq!(c.stream_position().unwrap(), 1);
+    /// ```
+    fn stream_position(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Current(0))
+    }
+
+    /// Returns the length of the stream in bytes.
+    ///
+    /// The length is determined by seeking to the end and back, so the
+    /// position is the same afterwards. Subtract `stream_position` to get the
+    /// number of bytes left.
+    ///
+    /// # Errors
+    ///
+    /// If seeking back fails, the position is left at the end of the stream.
+    fn stream_len(&mut self) -> Result<u64> {
+        let old_pos = try!(self.stream_position());
+        let len = try!(self.seek(SeekFrom::End(0)));
+        if old_pos != len {
+            try!(self.seek(SeekFrom::Start(old_pos)));
+        }
+        Ok(len)
+    }
 }
 
 /// Enumeration of possible methods to seek within an I/O object.
@@ -1333,6 +1479,41 @@ pub trait BufRead: Read {
         read_until(self, byte, buf)
     }
 
+    /// Like `read_until`, but reaching EOF before the delimiter `byte` is an
+    /// error.
+    ///
+    /// This is meant for protocols where a missing delimiter means the input
+    /// was truncated, so that a partial frame isn't mistaken for a complete
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// If EOF is reached before `byte` is found, including when the reader is
+    /// already at EOF, an error of the kind `ErrorKind::UnexpectedEof` is
+    /// returned. The bytes read up to EOF have been appended to `buf`. Other
+    /// errors are those of `read_until`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{self, Cursor};
+    /// use std::io::prelude::*;
+    ///
+    /// let mut c = Cursor::new(&b"ab;cd"[..]);
+    /// let mut frame = Vec::new();
+    /// c.read_until_strict(b';', &mut frame).unwrap();
+    /// assert_eq!(frame, b"ab;");
+    /// let err = c.read_until_strict(b';', &mut frame).unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    /// ```
+    fn read_until_strict(&mut self, byte: u8, buf: &mut Vec<u8>) -> Result<usize> {
+        let n = try!(read_until(self, byte, buf));
+        if n == 0 || buf[buf.len() - 1] != byte {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "delimiter not found"));
+        }
+        Ok(n)
+    }
+
     /// Read all bytes until a newline (the 0xA byte) is reached, and append
     /// them to the provided buffer.
     ///
@@ -1448,7 +1629,6 @@ pub struct Broadcast<T, U> {
     second: U,
 }
 
-#[allow(deprecated)]
 impl<T: Write, U: Write> Write for Broadcast<T, U> {
     fn write(&mut self, data: &[u8]) -> Result<usize> {
         let n = try!(self.first.write(data));
@@ -1486,6 +1666,47 @@ impl<T: Read, U: Read> Read for Chain<T, U> {
     }
 }
 
+impl<T, U> Chain<T, U> {
+    /// Consumes the `Chain`, returning the wrapped readers.
+    pub fn into_inner(self) -> (T, U) {
+        (self.first, self.second)
+    }
+
+    /// Gets references to the underlying readers in this `Chain`.
+    pub fn get_ref(&self) -> (&T, &U) {
+        (&self.first, &self.second)
+    }
+
+    /// Gets mutable references to the underlying readers in this `Chain`.
+    ///
+    /// Care should be taken to avoid modifying the internal I/O state of the
+    /// underlying readers as doing so may corrupt the internal state of this
+    /// `Chain`.
+    pub fn get_mut(&mut self) -> (&mut T, &mut U) {
+        (&mut self.first, &mut self.second)
+    }
+}
+
+impl<T: BufRead, U: BufRead> BufRead for Chain<T, U> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if !self.done_first {
+            match try!(self.first.fill_buf()) {
+                buf if buf.len() == 0 => { self.done_first = true; }
+                buf => return Ok(buf),
+            }
+        }
+        self.second.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if !self.done_first {
+            self.first.consume(amt)
+        } else {
+            self.second.consume(amt)
+        }
+    }
+}
+
 /// Reader adaptor which limits the bytes read from an underlying reader.
 ///
 /// This struct is generally created by calling [`take()`][take] on a reader.
@@ -1506,6 +1727,39 @@ impl<T> Take<T> {
     /// This instance may reach EOF after reading fewer bytes than indicated by
     /// this method if the underlying `Read` instance reaches EOF.
     pub fn limit(&self) -> u64 { self.limit }
+
+    /// Sets the number of bytes that can be read before this instance will
+    /// return EOF. This is the same as constructing a new `Take` instance, so
+    /// the amount of bytes read and the previous limit value don't matter when
+    /// calling this method.
+    pub fn set_limit(&mut self, limit: u64) { self.limit = limit; }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &T { &self.inner }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Care should be taken to avoid modifying the internal I/O state of the
+    /// underlying reader as doing so may corrupt the internal limit of this
+    /// `Take`.
+    pub fn get_mut(&mut self) -> &mut T { &mut self.inner }
+
+    /// Consumes the `Take`, returning the wrapped reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::prelude::*;
+    ///
+    /// let data = [1u8, 2, 3, 4, 5];
+    /// let mut take = (&data[..]).take(2);
+    /// let mut frame = [0; 2];
+    /// take.read_exact(&mut frame).unwrap();
+    ///
+    /// let rest = take.into_inner();
+    /// assert_eq!(rest, [3, 4, 5]);
+    /// ```
+    pub fn into_inner(self) -> T { self.inner }
 }
 
 impl<T: Read> Read for Take<T> {
@@ -1524,6 +1778,11 @@ impl<T: Read> Read for Take<T> {
 
 impl<T: BufRead> BufRead for Take<T> {
     fn fill_buf(&mut self) -> Result<&[u8]> {
+        // Don't call into inner reader at all at EOF because it may still block
+        if self.limit == 0 {
+            return Ok(&[]);
+        }
+
         let buf = try!(self.inner.fill_buf());
         let cap = cmp::min(buf.len() as u64, self.limit) as usize;
         Ok(&buf[..cap])
@@ -1548,7 +1807,6 @@ pub struct Tee<R, W> {
     writer: W,
 }
 
-#[allow(deprecated)]
 impl<R: Read, W: Write> Read for Tee<R, W> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         let n = try!(self.reader.read(buf));
@@ -1734,6 +1992,24 @@ mod tests {
         assert_eq!(v, []);
     }
 
+    #[test]
+    fn read_until_strict() {
+        let mut buf = Cursor::new(&b"12312"[..]);
+        let mut v = Vec::new();
+        assert_eq!(buf.read_until_strict(b'3', &mut v).unwrap(), 3);
+        assert_eq!(v, b"123");
+        let err = buf.read_until_strict(b'3', &mut v).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(v, b"12312");
+        let err = buf.read_until_strict(b'3', &mut v).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Doesn't mistake a delimiter already in `buf` for the end of a frame
+        let mut buf = Cursor::new(&b""[..]);
+        let mut v = b"3".to_vec();
+        assert!(buf.read_until_strict(b'3', &mut v).is_err());
+    }
+
     #[test]
     fn split() {
         let buf = Cursor::new(&b"12"[..]);
@@ -1793,11 +2069,26 @@ mod tests {
         assert_eq!(c.read_to_end(&mut v).unwrap(), 1);
         assert_eq!(v, b"1");
 
+        // Records the size of each read
+        struct R<'a> { inner: &'a [u8], reads: Vec<usize> }
+
+        impl<'a> Read for R<'a> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.reads.push(buf.len());
+                self.inner.read(buf)
+            }
+        }
+
         let cap = 1024 * 1024;
         let data = (0..cap).map(|i| (i / 3) as u8).collect::<Vec<_>>();
-        let mut v = Vec::new();
         let (a, b) = data.split_at(data.len() / 2);
-        assert_eq!(Cursor::new(a).read_to_end(&mut v).unwrap(), a.len());
+        let mut v = Vec::with_capacity(a.len());
+        let mut r = R { inner: a, reads: Vec::new() };
+        assert_eq!(r.read_to_end(&mut v).unwrap(), a.len());
+        // Once the vector is full, a small probe finds EOF instead of a read
+        // into a doubled vector
+        assert_eq!(r.reads, [a.len(), 32]);
+        assert_eq!(v, a);
         assert_eq!(Cursor::new(b).read_to_end(&mut v).unwrap(), b.len());
         assert_eq!(v, data);
     }
@@ -1860,6 +2151,45 @@ mod tests {
         assert_eq!(c, b"9");
     }
 
+    #[test]
+    fn write_all_vectored() {
+        let mut v = Vec::new();
+        v.write_all_vectored(&[b"\x05\x00", b"hello"]).unwrap();
+        assert_eq!(v, b"\x05\x00hello");
+
+        let mut buf = [0; 4];
+        let e = (&mut buf[..]).write_all_vectored(&[b"12", b"345"]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(&buf, b"1234");
+    }
+
+    #[test]
+    fn write_all_retry() {
+        // Writes 0 bytes twice before every successful write
+        struct Pipe { stalls: usize, data: Vec<u8> }
+
+        impl Write for Pipe {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.stalls += 1;
+                if self.stalls % 3 != 0 {
+                    return Ok(0);
+                }
+                self.data.push(buf[0]);
+                Ok(1)
+            }
+            fn flush(&mut self) -> io::Result<()> { Ok(()) }
+        }
+
+        let mut w = Pipe { stalls: 0, data: Vec::new() };
+        w.write_all_retry(b"abc", 2).unwrap();
+        assert_eq!(w.data, b"abc");
+
+        let mut w = Pipe { stalls: 0, data: Vec::new() };
+        let e = w.write_all_retry(b"abc", 1).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(w.stalls, 2);
+    }
+
     #[test]
     fn take_eof() {
         struct R;
@@ -1874,6 +2204,70 @@ mod tests {
         assert_eq!(0, R.take(0).read(&mut buf).unwrap());
     }
 
+    #[test]
+    fn chain_buf_read() {
+        let mut chain = (&b"ab"[..]).chain(&b"cd"[..]);
+        assert_eq!(chain.fill_buf().unwrap(), b"ab");
+        chain.consume(1);
+        assert_eq!(chain.fill_buf().unwrap(), b"b");
+        chain.consume(1);
+        assert_eq!(chain.fill_buf().unwrap(), b"cd");
+        chain.consume(1);
+
+        let mut s = String::new();
+        chain.read_line(&mut s).unwrap();
+        assert_eq!(s, "d");
+        assert_eq!(chain.fill_buf().unwrap(), b"");
+
+        let (first, second) = chain.into_inner();
+        assert_eq!(first, b"");
+        assert_eq!(second, b"");
+    }
+
+    #[test]
+    fn take_buf_read() {
+        let mut take = (&b"ab\ncd\nef"[..]).take(5);
+        assert_eq!(take.fill_buf().unwrap(), b"ab\ncd");
+        let mut v = Vec::new();
+        take.read_until(b'\n', &mut v).unwrap();
+        assert_eq!(v, b"ab\n");
+        assert_eq!(take.limit(), 2);
+        take.consume(10);
+        assert_eq!(take.limit(), 0);
+        assert_eq!(take.fill_buf().unwrap(), b"");
+        assert_eq!(take.into_inner(), b"cd\nef");
+
+        struct R;
+
+        impl Read for R {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> { unreachable!() }
+        }
+
+        impl BufRead for R {
+            fn fill_buf(&mut self) -> io::Result<&[u8]> { panic!("inner reader called at EOF") }
+            fn consume(&mut self, _: usize) {}
+        }
+
+        assert_eq!(R.take(0).fill_buf().unwrap(), b"");
+    }
+
+    #[test]
+    fn take_into_inner() {
+        let mut take = (&b"\x02\x00hello"[..]).take(2);
+        let mut len = [0; 2];
+        take.read_exact(&mut len).unwrap();
+        assert_eq!(take.limit(), 0);
+
+        take.set_limit(len[0] as u64);
+        assert_eq!(*take.get_ref(), b"hello");
+        let mut s = String::new();
+        take.read_to_string(&mut s).unwrap();
+        assert_eq!(s, "he");
+
+        let rest = take.into_inner();
+        assert_eq!(rest, b"llo");
+    }
+
     #[bench]
     fn bench_read_to_end(b: &mut test::Bencher) {
         b.iter(|| {
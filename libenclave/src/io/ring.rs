@@ -0,0 +1,205 @@
+/*
+ * The Rust secure enclave runtime and library.
+ *
+ * (C) Copyright 2016 Jethro G. Beekman
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ */
+
+//! A fixed-capacity in-memory byte queue.
+
+use rustc_alloc::boxed::Box;
+use core::cmp;
+use io::{self, Read, Write, BufRead};
+
+/// A fixed-capacity FIFO byte buffer.
+///
+/// Bytes written to a `RingBuffer` can be read back in the same order. The
+/// storage is allocated once by `with_capacity` and reused as data is
+/// drained, which makes this suitable for parsing data received in pieces
+/// without repeated allocation.
+///
+/// Writing to a full buffer returns `Ok(0)`, so `write_all` fails with
+/// `ErrorKind::WriteZero`; a `write` that doesn't fit is shortened. Reading
+/// from an empty buffer returns `Ok(0)`, like at the end of a stream.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::prelude::*;
+/// use std::io::RingBuffer;
+///
+/// let mut ring = RingBuffer::with_capacity(4);
+/// assert_eq!(ring.write(b"hello").unwrap(), 4);
+/// let mut buf = [0; 2];
+/// ring.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"he");
+/// ring.write_all(b"!!").unwrap();
+/// let mut s = String::new();
+/// ring.read_to_string(&mut s).unwrap();
+/// assert_eq!(s, "ll!!");
+/// ```
+pub struct RingBuffer {
+    buf: Box<[u8]>,
+    /// Position of the first readable byte
+    start: usize,
+    /// Number of readable bytes
+    len: usize,
+}
+
+impl RingBuffer {
+    /// Creates a new, empty `RingBuffer` that holds up to `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> RingBuffer {
+        RingBuffer { buf: vec![0; capacity].into_boxed_slice(), start: 0, len: 0 }
+    }
+
+    /// Returns the number of bytes the buffer can hold.
+    pub fn capacity(&self) -> usize { self.buf.len() }
+
+    /// Returns the number of bytes that can be read.
+    pub fn len(&self) -> usize { self.len }
+
+    /// Returns `true` if there are no bytes to read.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Returns `true` if writes would not accept any bytes.
+    pub fn is_full(&self) -> bool { self.len == self.buf.len() }
+
+    /// Removes all bytes from the buffer.
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+impl Read for RingBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // The readable bytes are in at most two contiguous parts
+        let mut nread = 0;
+        while nread < buf.len() && !self.is_empty() {
+            let n = {
+                let available = try!(self.fill_buf());
+                let n = cmp::min(available.len(), buf.len() - nread);
+                buf[nread..nread + n].copy_from_slice(&available[..n]);
+                n
+            };
+            self.consume(n);
+            nread += n;
+        }
+        Ok(nread)
+    }
+}
+
+impl BufRead for RingBuffer {
+    /// Returns the readable bytes up to the end of the underlying storage.
+    /// After consuming them, the rest is returned by the next call.
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let end = cmp::min(self.start + self.len, self.buf.len());
+        Ok(&self.buf[self.start..end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = cmp::min(amt, self.len);
+        self.len -= amt;
+        if self.len == 0 {
+            // Keep the data contiguous for as long as possible
+            self.start = 0;
+        } else {
+            self.start += amt;
+            if self.start >= self.buf.len() {
+                self.start -= self.buf.len();
+            }
+        }
+    }
+}
+
+impl Write for RingBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut nwritten = 0;
+        // The free space is in at most two contiguous parts
+        while nwritten < buf.len() && !self.is_full() {
+            let cap = self.buf.len();
+            let end = (self.start + self.len) % cap;
+            let free = if end < self.start { self.start - end } else { cap - end };
+            let n = cmp::min(free, buf.len() - nwritten);
+            self.buf[end..end + n].copy_from_slice(&buf[nwritten..nwritten + n]);
+            self.len += n;
+            nwritten += n;
+        }
+        Ok(nwritten)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::v1::*;
+
+    use io::prelude::*;
+    use io::{ErrorKind, RingBuffer};
+
+    #[test]
+    fn empty_and_full() {
+        let mut ring = RingBuffer::with_capacity(4);
+        assert!(ring.is_empty());
+        assert_eq!(ring.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(ring.fill_buf().unwrap(), &[][..]);
+
+        assert_eq!(ring.write(b"abcdef").unwrap(), 4);
+        assert!(ring.is_full());
+        assert_eq!(ring.write(b"g").unwrap(), 0);
+        assert_eq!(ring.write_all(b"g").unwrap_err().kind(), ErrorKind::WriteZero);
+
+        let mut buf = [0; 8];
+        assert_eq!(ring.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"abcd");
+        assert!(ring.is_empty());
+
+        let mut ring = RingBuffer::with_capacity(0);
+        assert!(ring.is_empty() && ring.is_full());
+        assert_eq!(ring.write(b"a").unwrap(), 0);
+        assert_eq!(ring.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn wraparound() {
+        let mut ring = RingBuffer::with_capacity(5);
+        ring.write_all(b"abcd").unwrap();
+        let mut buf = [0; 3];
+        ring.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"abc");
+
+        // Wraps around the end of the storage
+        ring.write_all(b"efgh").unwrap();
+        assert!(ring.is_full());
+        assert_eq!(ring.fill_buf().unwrap(), b"de");
+        ring.consume(1);
+        assert_eq!(ring.fill_buf().unwrap(), b"e");
+        ring.consume(1);
+        assert_eq!(ring.fill_buf().unwrap(), b"fgh");
+
+        ring.write_all(b"ij").unwrap();
+        let mut buf = [0; 5];
+        assert_eq!(ring.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"fghij");
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn lines() {
+        let mut ring = RingBuffer::with_capacity(8);
+        ring.write_all(b"ab\ncd").unwrap();
+        let mut line = String::new();
+        ring.read_line(&mut line).unwrap();
+        assert_eq!(line, "ab\n");
+        ring.write_all(b"e\nfg").unwrap();
+        line.clear();
+        ring.read_line(&mut line).unwrap();
+        assert_eq!(line, "cde\n");
+        assert_eq!(ring.len(), 2);
+    }
+}
@@ -0,0 +1,317 @@
+/*
+ * std::io implementation for core
+ *
+ * (C) Copyright 2015 The Rust Project Developers.
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or (at your
+ * option) any later version.
+ *
+ * This file incorporates work covered by the following copyright license:
+ *
+ *   Licensed under the Apache License, Version 2.0 (the "License"); you may
+ *   not use this file except in compliance with the License. You may obtain a
+ *   copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ */
+
+//! Standard input and output, forwarded to the host using usercalls.
+//!
+//! Reading and writing is done with the `USERCALL_READ` and `USERCALL_WRITE`
+//! usercalls, see the `usercall` module. The host is expected to handle these,
+//! e.g. using `enclave_interface::stdio::handle_usercall`.
+
+use io::prelude::*;
+
+use collections::{String, Vec};
+use core::fmt;
+use spin::{Mutex, MutexGuard};
+
+use io::{self, BufReader, LineWriter, Error, ErrorKind};
+use usercall::{do_usercall, UserSlice, USERCALL_READ, USERCALL_WRITE};
+
+const STDIN_FD: u64 = 0;
+const STDOUT_FD: u64 = 1;
+const STDERR_FD: u64 = 2;
+
+/// An unbuffered handle to one of the host's standard streams.
+struct StdRaw(u64);
+
+fn usercall_result(ret: u64, len: usize) -> io::Result<usize> {
+    let ret = ret as i64;
+    if ret < 0 {
+        // A negated Linux errno value. The host controls `ret`, so don't
+        // overflow on i64::MIN.
+        Err(Error::from_raw_os_error(ret.wrapping_neg() as i32))
+    } else if ret as u64 > len as u64 {
+        // Don't trust the host to report a sensible length
+        Err(Error::new(ErrorKind::InvalidData, "stdio usercall returned invalid length"))
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+impl Read for StdRaw {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() { return Ok(0) }
+        let user = UserSlice::<u8>::new_uninit(buf.len());
+        let ret = unsafe {
+            do_usercall(USERCALL_READ, self.0, user.as_ptr() as u64, buf.len() as u64, 0)
+        };
+        let n = try!(usercall_result(ret, buf.len()));
+        user.clone_into_enclave(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+impl Write for StdRaw {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() { return Ok(0) }
+        let user = UserSlice::clone_from(buf);
+        let ret = unsafe {
+            do_usercall(USERCALL_WRITE, self.0, user.as_ptr() as u64, buf.len() as u64, 0)
+        };
+        usercall_result(ret, buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+static STDIN: Mutex<Option<BufReader<StdRaw>>> = Mutex::new(None);
+static STDOUT: Mutex<Option<LineWriter<StdRaw>>> = Mutex::new(None);
+static STDERR: Mutex<StdRaw> = Mutex::new(StdRaw(STDERR_FD));
+
+/// A handle to the standard input stream of the host.
+///
+/// Each handle is a shared reference to a global buffer of input data to this
+/// enclave. Access is synchronized and can be explicitly controlled with the
+/// `lock()` method.
+///
+/// Created by the [`io::stdin`] method.
+///
+/// [`io::stdin`]: fn.stdin.html
+pub struct Stdin { _priv: () }
+
+/// A locked reference to the `Stdin` handle.
+///
+/// This handle implements both the `Read` and `BufRead` traits, and is
+/// constructed via the `lock` method on `Stdin`.
+pub struct StdinLock<'a> {
+    inner: MutexGuard<'a, Option<BufReader<StdRaw>>>,
+}
+
+/// Constructs a new handle to the standard input of the host.
+///
+/// The returned handle is buffered, the buffer is shared by all handles. The
+/// handle is cheap to create, the buffer is allocated on first use.
+pub fn stdin() -> Stdin {
+    Stdin { _priv: () }
+}
+
+impl Stdin {
+    /// Locks this handle to the standard input stream, returning a readable
+    /// guard.
+    ///
+    /// The lock is released when the returned lock goes out of scope. The
+    /// returned guard also implements the `Read` and `BufRead` traits for
+    /// accessing the underlying data.
+    pub fn lock(&self) -> StdinLock<'static> {
+        let mut inner = STDIN.lock();
+        if inner.is_none() {
+            *inner = Some(BufReader::new(StdRaw(STDIN_FD)));
+        }
+        StdinLock { inner: inner }
+    }
+
+    /// Locks this handle and reads a line of input into the specified buffer.
+    ///
+    /// See `BufRead::read_line` for more details.
+    pub fn read_line(&self, buf: &mut String) -> io::Result<usize> {
+        self.lock().read_line(buf)
+    }
+}
+
+impl fmt::Debug for Stdin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("Stdin { .. }")
+    }
+}
+
+impl Read for Stdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.lock().read(buf)
+    }
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.lock().read_to_end(buf)
+    }
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.lock().read_to_string(buf)
+    }
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.lock().read_exact(buf)
+    }
+}
+
+impl<'a> Read for StdinLock<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.as_mut().unwrap().read(buf)
+    }
+}
+
+impl<'a> BufRead for StdinLock<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> { self.inner.as_mut().unwrap().fill_buf() }
+    fn consume(&mut self, n: usize) { self.inner.as_mut().unwrap().consume(n) }
+}
+
+impl<'a> fmt::Debug for StdinLock<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("StdinLock { .. }")
+    }
+}
+
+/// A handle to the global standard output stream of the host.
+///
+/// Each handle shares a global buffer of data to be written to the standard
+/// output stream. Access is also synchronized via a lock and explicit control
+/// over locking is available via the `lock()` method.
+///
+/// Created by the [`io::stdout`] method.
+///
+/// [`io::stdout`]: fn.stdout.html
+pub struct Stdout { _priv: () }
+
+/// A locked reference to the `Stdout` handle.
+///
+/// This handle implements the `Write` trait, and is constructed via the `lock`
+/// method on `Stdout`. Output is line-buffered.
+pub struct StdoutLock<'a> {
+    inner: MutexGuard<'a, Option<LineWriter<StdRaw>>>,
+}
+
+/// Constructs a new handle to the standard output of the host.
+///
+/// The returned handle is line-buffered, the buffer is shared by all handles.
+/// The handle is cheap to create, the buffer is allocated on first use.
+pub fn stdout() -> Stdout {
+    Stdout { _priv: () }
+}
+
+impl Stdout {
+    /// Locks this handle to the standard output stream, returning a writable
+    /// guard.
+    ///
+    /// The lock is released when the returned lock goes out of scope. The
+    /// returned guard also implements the `Write` trait for writing data.
+    pub fn lock(&self) -> StdoutLock<'static> {
+        let mut inner = STDOUT.lock();
+        if inner.is_none() {
+            *inner = Some(LineWriter::new(StdRaw(STDOUT_FD)));
+        }
+        StdoutLock { inner: inner }
+    }
+}
+
+impl fmt::Debug for Stdout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("Stdout { .. }")
+    }
+}
+
+impl Write for Stdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.lock().write_all(buf)
+    }
+    fn write_fmt(&mut self, args: fmt::Arguments) -> io::Result<()> {
+        self.lock().write_fmt(args)
+    }
+}
+
+impl<'a> Write for StdoutLock<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.as_mut().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<'a> fmt::Debug for StdoutLock<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("StdoutLock { .. }")
+    }
+}
+
+/// A handle to the standard error stream of the host.
+///
+/// For more information, see the [`io::stderr`] method.
+///
+/// [`io::stderr`]: fn.stderr.html
+pub struct Stderr { _priv: () }
+
+/// A locked reference to the `Stderr` handle.
+///
+/// This handle implements the `Write` trait and is constructed via the `lock`
+/// method on `Stderr`.
+pub struct StderrLock<'a> {
+    inner: MutexGuard<'a, StdRaw>,
+}
+
+/// Constructs a new handle to the standard error of the host.
+///
+/// The returned handle is not buffered.
+pub fn stderr() -> Stderr {
+    Stderr { _priv: () }
+}
+
+impl Stderr {
+    /// Locks this handle to the standard error stream, returning a writable
+    /// guard.
+    ///
+    /// The lock is released when the returned lock goes out of scope. The
+    /// returned guard also implements the `Write` trait for writing data.
+    pub fn lock(&self) -> StderrLock<'static> {
+        StderrLock { inner: STDERR.lock() }
+    }
+}
+
+impl fmt::Debug for Stderr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("Stderr { .. }")
+    }
+}
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.lock().write_all(buf)
+    }
+    fn write_fmt(&mut self, args: fmt::Arguments) -> io::Result<()> {
+        self.lock().write_fmt(args)
+    }
+}
+
+impl<'a> Write for StderrLock<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<'a> fmt::Debug for StderrLock<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("StderrLock { .. }")
+    }
+}
@@ -17,6 +17,15 @@
 
 #![allow(missing_copy_implementations)]
 
+use core::cmp;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::str;
+use core::sync::atomic::{AtomicBool, Ordering};
+use collections::string::String;
+use collections::vec::Vec;
+use rustc_alloc::boxed::Box;
+use rustc_unicode::str as core_str;
 use io::{self, Read, Write, ErrorKind, BufRead};
 
 /// Copies the entire contents of a reader into a writer.
@@ -34,6 +43,17 @@ use io::{self, Read, Write, ErrorKind, BufRead};
 /// `write` returns an error. All instances of `ErrorKind::Interrupted` are
 /// handled by this function and the underlying operation is retried.
 ///
+/// # Stack usage
+///
+/// The copy buffer is 64 KiB (`DEFAULT_BUF_SIZE`), more than the stack of a
+/// typical enclave thread (see the `stack_size` setting of `link-sgxs`), so
+/// it is allocated on the heap on every call. Apart from that, this function
+/// needs only a few words of stack, plus whatever `reader.read` and
+/// `writer.write` use. To avoid the allocation, use
+/// [`copy_buf()`][copybuf] with a buffer of your choosing.
+///
+/// [copybuf]: fn.copy_buf.html
+///
 /// # Examples
 ///
 /// ```
@@ -52,18 +72,256 @@ use io::{self, Read, Write, ErrorKind, BufRead};
 pub fn copy<R: ?Sized, W: ?Sized>(reader: &mut R, writer: &mut W) -> io::Result<u64>
     where R: Read, W: Write
 {
-    let mut buf = [0; super::DEFAULT_BUF_SIZE];
+    let mut buf = copy_buffer();
+    copy_loop(reader, writer, &mut buf, None, None, |_| {})
+}
+
+/// Copies the entire contents of a reader into a writer, using a buffer
+/// provided by the caller.
+///
+/// This behaves like `copy`, but doesn't allocate a buffer.
+/// Data is read into `buf` and then written out, so the size of `buf`
+/// determines the maximum size of each `read` and `write`.
+///
+/// # Stack usage
+///
+/// Nothing is allocated. This function needs only a few words of stack,
+/// plus whatever `reader.read` and `writer.write` use, and `buf` itself if
+/// the caller put it on the stack.
+///
+/// # Panics
+///
+/// Panics if `buf` is empty.
+pub fn copy_buf<R: ?Sized, W: ?Sized>(reader: &mut R, writer: &mut W, buf: &mut [u8]) -> io::Result<u64>
+    where R: Read, W: Write
+{
+    assert!(!buf.is_empty(), "copy_buf called with an empty buffer");
+    copy_loop(reader, writer, buf, None, None, |_| {})
+}
+
+/// The read loop behind all `copy` functions.
+///
+/// Copies from `reader` to `writer` through `buf` until EOF, or until `limit`
+/// bytes have been copied if it is given. Reads are never larger than the
+/// remaining limit. `ErrorKind::Interrupted` errors from `read` are retried,
+/// at most `max_interrupts` times in a row if it is given. `progress` is
+/// called with the total number of bytes copied after each write.
+fn copy_loop<R: ?Sized, W: ?Sized, F>(reader: &mut R, writer: &mut W, buf: &mut [u8],
+                                      limit: Option<u64>, max_interrupts: Option<usize>,
+                                      mut progress: F) -> io::Result<u64>
+    where R: Read, W: Write, F: FnMut(u64)
+{
     let mut written = 0;
+    let mut interrupts = 0;
     loop {
-        let len = match reader.read(&mut buf) {
+        let max = match limit {
+            Some(limit) if written >= limit => return Ok(written),
+            Some(limit) => cmp::min(buf.len() as u64, limit - written) as usize,
+            None => buf.len(),
+        };
+        let len = match reader.read(&mut buf[..max]) {
             Ok(0) => return Ok(written),
             Ok(len) => len,
-            Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted &&
+                          max_interrupts.map_or(true, |max| interrupts < max) => {
+                interrupts += 1;
+                continue
+            }
             Err(e) => return Err(e),
         };
+        interrupts = 0;
         try!(writer.write_all(&buf[..len]));
         written += len as u64;
+        progress(written);
+    }
+}
+
+fn copy_buffer() -> Vec<u8> {
+    vec![0; super::DEFAULT_BUF_SIZE]
+}
+
+/// Copies the entire contents of a reader into a writer, reporting progress.
+///
+/// This behaves like `copy`, except that `progress` is called with the total
+/// number of bytes copied so far after each chunk has been written. It is not
+/// called for reads that are retried because of `ErrorKind::Interrupted`.
+///
+/// On success, the total number of bytes that were copied from `reader` to
+/// `writer` is returned.
+///
+/// # Errors
+///
+/// This function will return an error immediately if any call to `read` or
+/// `write` returns an error. All instances of `ErrorKind::Interrupted` are
+/// handled by this function and the underlying operation is retried.
+///
+/// # Stack usage
+///
+/// Like `copy`, this allocates the 64 KiB copy buffer on the heap on every
+/// call, and otherwise needs only a few words of stack plus whatever
+/// `reader.read` and `writer.write` use.
+pub fn copy_with<R: ?Sized, W: ?Sized, F>(reader: &mut R, writer: &mut W, progress: F) -> io::Result<u64>
+    where R: Read, W: Write, F: FnMut(u64)
+{
+    let mut buf = copy_buffer();
+    copy_loop(reader, writer, &mut buf, None, None, progress)
+}
+
+/// Copies the entire contents of a reader into a writer, giving up after too
+/// many consecutive interruptions.
+///
+/// This behaves like `copy`, except that `ErrorKind::Interrupted` errors
+/// returned by `read` are only retried `max_interrupts` times in a row. A
+/// successful read resets the count. This prevents looping forever when the
+/// host keeps interrupting the transfer.
+///
+/// # Errors
+///
+/// This function will return an error immediately if any call to `read` or
+/// `write` returns an error other than `ErrorKind::Interrupted`. If `read`
+/// returns `ErrorKind::Interrupted` more than `max_interrupts` times in a
+/// row, the last such error is returned.
+///
+/// # Stack usage
+///
+/// Like `copy`, this allocates the 64 KiB copy buffer on the heap on every
+/// call, and otherwise needs only a few words of stack plus whatever
+/// `reader.read` and `writer.write` use.
+pub fn copy_with_retries<R: ?Sized, W: ?Sized>(reader: &mut R, writer: &mut W, max_interrupts: usize) -> io::Result<u64>
+    where R: Read, W: Write
+{
+    let mut buf = copy_buffer();
+    copy_loop(reader, writer, &mut buf, None, Some(max_interrupts), |_| {})
+}
+
+/// Copies at most `limit` bytes from a reader into a writer.
+///
+/// This behaves like `copy`, except that it stops once `limit` bytes have
+/// been copied. The reader is never asked for more than the remaining number
+/// of bytes, so it is left positioned right after the last copied byte.
+///
+/// On success, the total number of bytes that were copied from `reader` to
+/// `writer` is returned. This is less than `limit` if `reader` returned EOF
+/// first.
+///
+/// # Errors
+///
+/// This function will return an error immediately if any call to `read` or
+/// `write` returns an error. All instances of `ErrorKind::Interrupted` are
+/// handled by this function and the underlying operation is retried.
+///
+/// # Stack usage
+///
+/// Like `copy`, this allocates the 64 KiB copy buffer on the heap on every
+/// call, and otherwise needs only a few words of stack plus whatever
+/// `reader.read` and `writer.write` use.
+pub fn copy_bounded<R: ?Sized, W: ?Sized>(reader: &mut R, writer: &mut W, limit: u64) -> io::Result<u64>
+    where R: Read, W: Write
+{
+    let mut buf = copy_buffer();
+    copy_loop(reader, writer, &mut buf, Some(limit), None, |_| {})
+}
+
+/// Reads from `reader` until `buf` is full or EOF is reached.
+///
+/// Unlike `Read::read_exact`, reaching EOF before `buf` is full is not an
+/// error. This is useful with readers that return short reads, such as those
+/// backed by usercalls.
+///
+/// On success, the number of bytes read into `buf` is returned. This is less
+/// than `buf.len()` only if `reader` returned EOF.
+///
+/// # Errors
+///
+/// This function will return an error immediately if any call to `read`
+/// returns an error. All instances of `ErrorKind::Interrupted` are handled by
+/// this function and the underlying operation is retried. Bytes read before
+/// the error are left in `buf`.
+pub fn read_buf_fully<R: ?Sized + Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
     }
+    Ok(filled)
+}
+
+/// Reads all bytes until EOF in `reader`, appending them to `buf` while
+/// validating UTF-8 as the data arrives.
+///
+/// Unlike [`Read::read_to_string()`][readtostring], which only validates once
+/// the whole stream has been read, this function stops reading as soon as an
+/// invalid byte sequence is encountered. This bounds the amount of data read
+/// from an untrusted source that does not contain text.
+///
+/// On success, the number of bytes appended to `buf` is returned.
+///
+/// [readtostring]: trait.Read.html#method.read_to_string
+///
+/// # Errors
+///
+/// If the stream contains invalid UTF-8, an error of the kind `InvalidData`
+/// is returned. If `read` returns an error, that error is returned. All
+/// instances of `ErrorKind::Interrupted` are handled by this function and the
+/// underlying operation is retried. In the error case, `buf` contains the
+/// valid UTF-8 read before the error.
+///
+/// # Examples
+///
+/// ```
+/// use std::io;
+///
+/// # fn foo() -> io::Result<()> {
+/// let mut buffer = String::new();
+/// let mut reader = &b"caf\xc3\xa9"[..];
+/// try!(io::read_to_string_strict(&mut reader, &mut buffer));
+/// assert_eq!(buffer, "caf\u{e9}");
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_to_string_strict<R: ?Sized + Read>(reader: &mut R, buf: &mut String) -> io::Result<usize> {
+    let start_len = buf.len();
+    let mut chunk = [0; 4096];
+    // Number of bytes at the start of `chunk` that are the beginning of a
+    // multi-byte sequence that was cut off by the previous read.
+    let mut pending = 0;
+    loop {
+        let end = match reader.read(&mut chunk[pending..]) {
+            Ok(0) if pending == 0 => return Ok(buf.len() - start_len),
+            Ok(0) => return Err(invalid_utf8()),
+            Ok(n) => pending + n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let valid = match str::from_utf8(&chunk[..end]) {
+            Ok(_) => end,
+            Err(e) => e.valid_up_to(),
+        };
+        // `from_utf8` just validated these bytes
+        buf.push_str(unsafe { str::from_utf8_unchecked(&chunk[..valid]) });
+        if valid < end && !is_incomplete_utf8(&chunk[valid..end]) {
+            return Err(invalid_utf8());
+        }
+        pending = end - valid;
+        for i in 0..pending {
+            chunk[i] = chunk[valid + i];
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
+
+/// Whether `bytes`, which failed UTF-8 validation, could be the start of a
+/// valid sequence that continues in the next read.
+fn is_incomplete_utf8(bytes: &[u8]) -> bool {
+    let width = core_str::utf8_char_width(bytes[0]);
+    width != 0 && bytes.len() < width && bytes[1..].iter().all(|&b| b & 0xc0 == 0x80)
 }
 
 /// A reader which is always at EOF.
@@ -108,13 +366,13 @@ impl BufRead for Empty {
 /// see the documentation of `repeat()` for more details.
 ///
 /// [repeat]: fn.repeat.html
-pub struct Repeat { byte: u8 }
+pub struct Repeat { byte: u8, buf: [u8; 256] }
 
 /// Creates an instance of a reader that infinitely repeats one byte.
 ///
 /// All reads from this reader will succeed by filling the specified buffer with
 /// the given byte.
-pub fn repeat(byte: u8) -> Repeat { Repeat { byte: byte } }
+pub fn repeat(byte: u8) -> Repeat { Repeat { byte: byte, buf: [byte; 256] } }
 
 impl Read for Repeat {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -125,6 +383,57 @@ impl Read for Repeat {
     }
 }
 
+impl BufRead for Repeat {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> { Ok(&self.buf) }
+    // The source is infinite, there is nothing to consume
+    fn consume(&mut self, _n: usize) {}
+}
+
+/// A reader which yields a sequence of bytes over and over.
+///
+/// This struct is generally created by calling [`repeat_pattern()`][repeat_pattern].
+/// Please see the documentation of `repeat_pattern()` for more details.
+///
+/// [repeat_pattern]: fn.repeat_pattern.html
+pub struct RepeatPattern { pattern: Vec<u8>, pos: usize }
+
+/// Creates an instance of a reader that infinitely repeats `pattern`.
+///
+/// All reads from this reader will succeed by filling the specified buffer
+/// with the pattern. A read that ends in the middle of the pattern is
+/// continued by the next read.
+///
+/// # Panics
+///
+/// Panics if `pattern` is empty.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Read};
+///
+/// let mut buf = [0; 5];
+/// io::repeat_pattern(b"ab").read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"ababa");
+/// ```
+pub fn repeat_pattern(pattern: &[u8]) -> RepeatPattern {
+    assert!(!pattern.is_empty(), "repeat_pattern: empty pattern");
+    RepeatPattern { pattern: pattern.to_vec(), pos: 0 }
+}
+
+impl Read for RepeatPattern {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = cmp::min(self.pattern.len() - self.pos, buf.len() - filled);
+            buf[filled..filled + n].copy_from_slice(&self.pattern[self.pos..self.pos + n]);
+            filled += n;
+            self.pos = (self.pos + n) % self.pattern.len();
+        }
+        Ok(buf.len())
+    }
+}
+
 /// A writer which will move data into the void.
 ///
 /// This struct is generally created by calling [`sink()`][sink]. Please
@@ -144,12 +453,347 @@ impl Write for Sink {
     fn flush(&mut self) -> io::Result<()> { Ok(()) }
 }
 
+/// A reader which returns at most a fixed number of bytes per `read` call.
+///
+/// This is useful for testing code that wrongly assumes `read` fills the
+/// whole buffer, as can happen with slow host input.
+pub struct SlowReader<R> {
+    inner: R,
+    max_per_read: usize,
+}
+
+impl<R: Read> SlowReader<R> {
+    /// Creates a new `SlowReader` that reads at most `max_per_read` bytes
+    /// from `inner` per `read` call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_per_read` is 0. Every `read` would return `Ok(0)`,
+    /// which callers take to mean EOF.
+    pub fn new(inner: R, max_per_read: usize) -> SlowReader<R> {
+        assert!(max_per_read > 0, "SlowReader::new called with max_per_read == 0");
+        SlowReader { inner: inner, max_per_read: max_per_read }
+    }
+
+    /// Unwraps this `SlowReader`, returning the underlying reader.
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+impl<R: Read> Read for SlowReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = cmp::min(buf.len(), self.max_per_read);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+/// A writer which counts the bytes written to an underlying writer.
+///
+/// Only bytes that the underlying writer reports as written are counted.
+/// Writing to `sink()` through a `CountWriter` computes the size of some
+/// output without storing it.
+pub struct CountWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> CountWriter<W> {
+    /// Creates a new `CountWriter` writing to `inner`, with a count of 0.
+    pub fn new(inner: W) -> CountWriter<W> {
+        CountWriter { inner: inner, count: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn count(&self) -> u64 { self.count }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W { &self.inner }
+
+    /// Unwraps this `CountWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W { self.inner }
+}
+
+impl<W: Write> Write for CountWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = try!(self.inner.write(buf));
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+}
+
+/// A writer which passes at most a fixed number of bytes to an underlying
+/// writer.
+///
+/// This is the writing counterpart of `Read::take`. A `write` that would go
+/// past the limit is shortened to the remaining bytes. Once the limit is
+/// reached, writes of non-empty buffers fail with `ErrorKind::WriteZero`, so
+/// `write_all` fails without the underlying writer seeing any of the excess
+/// bytes.
+pub struct LimitWriter<W> {
+    inner: W,
+    limit: u64,
+}
+
+impl<W: Write> LimitWriter<W> {
+    /// Creates a new `LimitWriter` that writes at most `limit` bytes to
+    /// `inner`.
+    pub fn new(inner: W, limit: u64) -> LimitWriter<W> {
+        LimitWriter { inner: inner, limit: limit }
+    }
+
+    /// Returns the number of bytes that can be written before this instance
+    /// fails.
+    pub fn remaining(&self) -> u64 { self.limit }
+
+    /// Sets the number of bytes that can be written before this instance
+    /// fails, regardless of how many bytes have been written so far.
+    pub fn set_limit(&mut self, limit: u64) { self.limit = limit; }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W { &self.inner }
+
+    /// Unwraps this `LimitWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W { self.inner }
+}
+
+impl<W: Write> Write for LimitWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.limit == 0 && !buf.is_empty() {
+            return Err(io::Error::new(ErrorKind::WriteZero, "write limit reached"));
+        }
+        let len = cmp::min(buf.len() as u64, self.limit) as usize;
+        let n = try!(self.inner.write(&buf[..len]));
+        self.limit -= n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+}
+
+/// A reader or writer whose operations can be canceled from another thread.
+///
+/// Once the `canceled` flag is set, every `read`, `write` and `flush` fails
+/// with `ErrorKind::Canceled`. Since that error is not `Interrupted`, it is
+/// not retried by `copy`, `read_to_end`, `write_all` and the like, so they
+/// stop as well. An `Interrupted` error from the inner stream is also
+/// reported as `Canceled` if the flag was set in the meantime.
+///
+/// Cancellation is cooperative, not preemptive: the flag is only checked
+/// before and after calling the inner stream. A call that is blocked in the
+/// inner stream, such as a usercall waiting for input, is not interrupted.
+pub struct Cancelable<'a, T> {
+    inner: T,
+    canceled: &'a AtomicBool,
+}
+
+impl<'a, T> Cancelable<'a, T> {
+    /// Creates a new `Cancelable` wrapping `inner`, which is canceled once
+    /// `canceled` is set to `true`.
+    pub fn new(inner: T, canceled: &'a AtomicBool) -> Cancelable<'a, T> {
+        Cancelable { inner: inner, canceled: canceled }
+    }
+
+    /// Returns whether the operations have been canceled.
+    pub fn is_canceled(&self) -> bool { self.canceled.load(Ordering::SeqCst) }
+
+    /// Gets a reference to the underlying reader or writer.
+    pub fn get_ref(&self) -> &T { &self.inner }
+
+    /// Gets a mutable reference to the underlying reader or writer.
+    pub fn get_mut(&mut self) -> &mut T { &mut self.inner }
+
+    /// Unwraps this `Cancelable`, returning the underlying reader or writer.
+    pub fn into_inner(self) -> T { self.inner }
+
+    fn check<U>(&self, result: io::Result<U>) -> io::Result<U> {
+        match result {
+            Err(ref e) if e.kind() == ErrorKind::Interrupted && self.is_canceled() => Err(canceled()),
+            result => result,
+        }
+    }
+}
+
+fn canceled() -> io::Error {
+    io::Error::new(ErrorKind::Canceled, "operation canceled")
+}
+
+impl<'a, R: Read> Read for Cancelable<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.is_canceled() { return Err(canceled()) }
+        let result = self.inner.read(buf);
+        self.check(result)
+    }
+}
+
+impl<'a, W: Write> Write for Cancelable<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.is_canceled() { return Err(canceled()) }
+        let result = self.inner.write(buf);
+        self.check(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.is_canceled() { return Err(canceled()) }
+        let result = self.inner.flush();
+        self.check(result)
+    }
+}
+
+/// A hash function or checksum that processes its input incrementally.
+///
+/// This is what `HashingReader` and `HashingWriter` feed the data to.
+pub trait Digest {
+    /// The type of the final hash value.
+    type Output;
+
+    /// Processes `data` as the next part of the input.
+    fn update(&mut self, data: &[u8]);
+
+    /// Consumes the hasher, returning the hash of all input.
+    fn finalize(self) -> Self::Output;
+}
+
+/// A reader which hashes all bytes read from an underlying reader.
+///
+/// Reading through a `HashingReader` with `copy()` copies and hashes the
+/// data in a single pass.
+pub struct HashingReader<R, H> {
+    inner: R,
+    hasher: H,
+}
+
+impl<R: Read, H: Digest> HashingReader<R, H> {
+    /// Creates a new `HashingReader` reading from `inner` and feeding the
+    /// data read to `hasher`.
+    pub fn new(inner: R, hasher: H) -> HashingReader<R, H> {
+        HashingReader { inner: inner, hasher: hasher }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R { &self.inner }
+
+    /// Gets a reference to the hasher.
+    pub fn hasher(&self) -> &H { &self.hasher }
+
+    /// Returns the hash of all bytes read so far.
+    pub fn finalize(self) -> H::Output { self.hasher.finalize() }
+
+    /// Unwraps this `HashingReader`, returning the underlying reader and the
+    /// hasher.
+    pub fn into_inner(self) -> (R, H) { (self.inner, self.hasher) }
+}
+
+impl<R: Read, H: Digest> Read for HashingReader<R, H> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.inner.read(buf));
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A writer which hashes all bytes written to an underlying writer.
+///
+/// Only bytes that the underlying writer reports as written are hashed.
+pub struct HashingWriter<W, H> {
+    inner: W,
+    hasher: H,
+}
+
+impl<W: Write, H: Digest> HashingWriter<W, H> {
+    /// Creates a new `HashingWriter` writing to `inner` and feeding the data
+    /// written to `hasher`.
+    pub fn new(inner: W, hasher: H) -> HashingWriter<W, H> {
+        HashingWriter { inner: inner, hasher: hasher }
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W { &self.inner }
+
+    /// Gets a reference to the hasher.
+    pub fn hasher(&self) -> &H { &self.hasher }
+
+    /// Returns the hash of all bytes written so far.
+    pub fn finalize(self) -> H::Output { self.hasher.finalize() }
+
+    /// Unwraps this `HashingWriter`, returning the underlying writer and the
+    /// hasher.
+    pub fn into_inner(self) -> (W, H) { (self.inner, self.hasher) }
+}
+
+impl<W: Write, H: Digest> Write for HashingWriter<W, H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = try!(self.inner.write(buf));
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+}
+
+/// A fixed-size heap buffer for secrets that is overwritten with zeros when
+/// dropped.
+///
+/// The buffer dereferences to `[u8]`, so it can be passed to `read`,
+/// `read_exact` and the like, see also `read_secret`. Its size never
+/// changes, so no copies of the contents are left behind by reallocation.
+/// The zeroing uses volatile writes so it isn't optimized out even though
+/// the buffer is not read afterwards.
+///
+/// Copies made by the user, e.g. by moving the contents into a `Vec`, are
+/// not covered.
+pub struct ZeroizeBuf { buf: Box<[u8]> }
+
+impl ZeroizeBuf {
+    /// Creates a zero-filled buffer of `len` bytes.
+    pub fn new(len: usize) -> ZeroizeBuf {
+        ZeroizeBuf { buf: vec![0; len].into_boxed_slice() }
+    }
+
+    /// Overwrites the contents with zeros. This is done automatically on
+    /// drop.
+    pub fn zeroize(&mut self) {
+        for byte in self.buf.iter_mut() {
+            unsafe { ptr::write_volatile(byte, 0) };
+        }
+    }
+}
+
+impl Deref for ZeroizeBuf {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] { &self.buf }
+}
+
+impl DerefMut for ZeroizeBuf {
+    fn deref_mut(&mut self) -> &mut [u8] { &mut self.buf }
+}
+
+impl Drop for ZeroizeBuf {
+    fn drop(&mut self) { self.zeroize() }
+}
+
+/// Fills `buf` from `reader` with `read_exact`.
+///
+/// If reading fails, the part of `buf` that was read is zeroed before
+/// returning the error, so a partial secret doesn't linger until `buf` is
+/// dropped.
+pub fn read_secret<R: ?Sized + Read>(reader: &mut R, buf: &mut ZeroizeBuf) -> io::Result<()> {
+    let result = reader.read_exact(buf);
+    if result.is_err() {
+        buf.zeroize();
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use prelude::v1::*;
 
+    use core::cmp;
     use io::prelude::*;
-    use io::{copy, sink, empty, repeat};
+    use io::{self, copy, copy_buf, copy_bounded, copy_with, copy_with_retries, read_buf_fully, read_to_string_strict, sink, empty, repeat, repeat_pattern, ErrorKind, SlowReader, CountWriter, LimitWriter};
+    use io::{Digest, HashingReader, HashingWriter, Cancelable, ZeroizeBuf, read_secret};
+    use core::sync::atomic::{AtomicBool, Ordering};
 
     #[test]
     fn copy_copies() {
@@ -161,6 +805,259 @@ mod tests {
         assert_eq!(copy(&mut r as &mut Read, &mut w as &mut Write).unwrap(), 1 << 17);
     }
 
+    struct Interrupter { reads: usize }
+
+    impl Read for Interrupter {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            Err(io::Error::new(ErrorKind::Interrupted, "interrupted"))
+        }
+    }
+
+    #[test]
+    fn copy_with_retries_gives_up() {
+        let mut r = Interrupter { reads: 0 };
+        let mut w = sink();
+        let err = copy_with_retries(&mut r, &mut w, 5).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+        assert_eq!(r.reads, 6);
+
+        let mut r = repeat(0).take(1 << 17);
+        assert_eq!(copy_with_retries(&mut r, &mut w, 0).unwrap(), 1 << 17);
+    }
+
+    #[test]
+    fn copy_with_reports_progress() {
+        struct Flaky { interrupt: bool, data: &'static [u8] }
+
+        impl Read for Flaky {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.interrupt = !self.interrupt;
+                if self.interrupt {
+                    return Err(io::Error::new(ErrorKind::Interrupted, "interrupted"));
+                }
+                let n = cmp::min(3, buf.len());
+                self.data.read(&mut buf[..n])
+            }
+        }
+
+        let mut r = Flaky { interrupt: false, data: b"abcdefgh" };
+        let mut w = Vec::new();
+        let mut reports = Vec::new();
+        assert_eq!(copy_with(&mut r, &mut w, |n| reports.push(n)).unwrap(), 8);
+        assert_eq!(w, b"abcdefgh");
+        assert_eq!(reports, [3, 6, 8]);
+    }
+
+    #[test]
+    fn copy_buf_small_buffer() {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut buf = [0; 4];
+        for _ in 0..100 {
+            let mut r = &data[..];
+            let mut w = Vec::new();
+            assert_eq!(copy_buf(&mut r, &mut w, &mut buf).unwrap(), 1000);
+            assert_eq!(w, data);
+        }
+    }
+
+    #[test]
+    fn copy_bounded_stops_at_limit() {
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let mut r = &data[..];
+        let mut w = Vec::new();
+        assert_eq!(copy_bounded(&mut r, &mut w, 4).unwrap(), 4);
+        assert_eq!(w, [1, 2, 3, 4]);
+        assert_eq!(r, [5, 6, 7, 8, 9, 10]);
+
+        assert_eq!(copy_bounded(&mut r, &mut w, 100).unwrap(), 6);
+        assert_eq!(w, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(copy_bounded(&mut r, &mut w, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn read_buf_fully_short_reads() {
+        let data = [1u8, 2, 3, 4, 5, 6];
+        let mut r = SlowReader::new(&data[..], 1);
+        let mut buf = [0; 4];
+        assert_eq!(read_buf_fully(&mut r, &mut buf).unwrap(), 4);
+        assert_eq!(buf, [1, 2, 3, 4]);
+        assert_eq!(read_buf_fully(&mut r, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], [5, 6]);
+        assert_eq!(read_buf_fully(&mut r, &mut buf).unwrap(), 0);
+        assert_eq!(read_buf_fully(&mut r, &mut []).unwrap(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slow_reader_zero_per_read() {
+        SlowReader::new(&b"abc"[..], 0);
+    }
+
+    #[test]
+    fn read_to_string_strict_split_sequences() {
+        let data = "a\u{e9}\u{20ac}\u{1f600}z".as_bytes();
+        let mut r = SlowReader::new(data, 1);
+        let mut s = String::from("x");
+        assert_eq!(read_to_string_strict(&mut r, &mut s).unwrap(), data.len());
+        assert_eq!(s, "xa\u{e9}\u{20ac}\u{1f600}z");
+    }
+
+    #[test]
+    fn read_to_string_strict_fails_fast() {
+        let mut data = b"ok\xff".to_vec();
+        data.extend(vec![b'a'; 1 << 16]);
+        let mut r = io::Cursor::new(&data[..]);
+        let mut s = String::new();
+        let e = read_to_string_strict(&mut r, &mut s).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        assert_eq!(s, "ok");
+        assert!(r.position() < data.len() as u64);
+
+        let mut s = String::new();
+        assert!(read_to_string_strict(&mut &b"ab\xe2\x82"[..], &mut s).is_err());
+        assert_eq!(s, "ab");
+    }
+
+    #[test]
+    fn count_writer() {
+        struct ShortWriter(Vec<u8>);
+
+        impl Write for ShortWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let n = cmp::min(buf.len(), 3);
+                self.0.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+            fn flush(&mut self) -> io::Result<()> { Ok(()) }
+        }
+
+        let mut w = CountWriter::new(ShortWriter(Vec::new()));
+        assert_eq!(w.write(b"hello").unwrap(), 3);
+        assert_eq!(w.count(), 3);
+        w.write_all(b"lo, world").unwrap();
+        write!(w, "{}", 42).unwrap();
+        w.flush().unwrap();
+        assert_eq!(w.count(), 14);
+        assert_eq!(w.into_inner().0, b"hello, world42");
+
+        let mut w = CountWriter::new(sink());
+        copy(&mut repeat(0).take(1000), &mut w).unwrap();
+        assert_eq!(w.count(), 1000);
+    }
+
+    #[test]
+    fn limit_writer() {
+        let mut w = LimitWriter::new(Vec::new(), 10);
+        w.write_all(b"hello").unwrap();
+        assert_eq!(w.remaining(), 5);
+        assert_eq!(w.write(b"worlds").unwrap(), 5);
+        assert_eq!(w.remaining(), 0);
+        assert_eq!(w.write(b"").unwrap(), 0);
+        assert_eq!(w.write(b"!").unwrap_err().kind(), ErrorKind::WriteZero);
+        assert_eq!(w.get_ref(), b"helloworld");
+
+        w.set_limit(3);
+        assert_eq!(w.write_all(b"abcd").unwrap_err().kind(), ErrorKind::WriteZero);
+        assert_eq!(w.remaining(), 0);
+        assert_eq!(w.into_inner(), b"helloworldabc");
+    }
+
+    #[test]
+    fn zeroize_buf() {
+        let mut buf = ZeroizeBuf::new(4);
+        assert_eq!(&buf[..], &[0u8; 4]);
+        read_secret(&mut &b"keys"[..], &mut buf).unwrap();
+        assert_eq!(&buf[..], b"keys");
+        buf.zeroize();
+        assert_eq!(&buf[..], &[0u8; 4]);
+
+        let mut r = &b"ke"[..];
+        assert_eq!(read_secret(&mut r, &mut buf).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(&buf[..], &[0u8; 4]);
+    }
+
+    #[test]
+    fn cancelable_stops_copy() {
+        // Cancels on the third read, by which point copy has seen an
+        // interruption it would normally retry
+        struct Canceler<'a> { reads: usize, flag: &'a AtomicBool }
+
+        impl<'a> Read for Canceler<'a> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.reads += 1;
+                match self.reads {
+                    1 => { buf[0] = 1; Ok(1) }
+                    2 => Err(io::Error::new(ErrorKind::Interrupted, "interrupted")),
+                    _ => {
+                        self.flag.store(true, Ordering::SeqCst);
+                        Err(io::Error::new(ErrorKind::Interrupted, "interrupted"))
+                    }
+                }
+            }
+        }
+
+        let flag = AtomicBool::new(false);
+        let mut r = Cancelable::new(Canceler { reads: 0, flag: &flag }, &flag);
+        let mut w = Vec::new();
+        assert_eq!(copy(&mut r, &mut w).unwrap_err().kind(), ErrorKind::Canceled);
+        assert_eq!(w, [1u8]);
+        assert_eq!(r.get_ref().reads, 3);
+        assert!(r.read(&mut [0]).is_err());
+        assert_eq!(r.into_inner().reads, 3);
+
+        let flag = AtomicBool::new(true);
+        let mut w = Cancelable::new(Vec::new(), &flag);
+        assert_eq!(w.write(b"x").unwrap_err().kind(), ErrorKind::Canceled);
+        assert!(w.get_ref().is_empty());
+    }
+
+    // FNV-1a
+    struct Fnv(u64);
+
+    impl Digest for Fnv {
+        type Output = u64;
+        fn update(&mut self, data: &[u8]) {
+            for &b in data {
+                self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+            }
+        }
+        fn finalize(self) -> u64 { self.0 }
+    }
+
+    const FNV_INIT: u64 = 0xcbf29ce484222325;
+
+    #[test]
+    fn hashing_reader_writer() {
+        let data: Vec<u8> = (0..100000).map(|i| (i * 7) as u8).collect();
+        let mut expected = Fnv(FNV_INIT);
+        expected.update(&data);
+        let expected = expected.finalize();
+
+        let mut r = HashingReader::new(SlowReader::new(&data[..], 1000), Fnv(FNV_INIT));
+        let mut out = Vec::new();
+        assert_eq!(copy(&mut r, &mut out).unwrap(), data.len() as u64);
+        assert_eq!(r.finalize(), expected);
+        assert!(out == data);
+
+        let mut w = HashingWriter::new(Vec::new(), Fnv(FNV_INIT));
+        copy(&mut &data[..], &mut w).unwrap();
+        let (out, hasher) = w.into_inner();
+        assert_eq!(hasher.finalize(), expected);
+        assert!(out == data);
+    }
+
+    #[test]
+    fn slow_reader() {
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let mut r = SlowReader::new(&data[..], 3);
+        let mut buf = [0; 8];
+        assert_eq!(r.read(&mut buf).unwrap(), 3);
+        r.read_exact(&mut buf[3..]).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(r.into_inner(), [9, 10]);
+    }
+
     #[test]
     fn sink_sinks() {
         let mut s = sink();
@@ -187,6 +1084,37 @@ mod tests {
         assert!(b.iter().all(|b| *b == 4));
     }
 
+    #[test]
+    fn repeat_pattern_resumes() {
+        let mut r = repeat_pattern(b"abc");
+        let mut b = [0; 5];
+        assert_eq!(r.read(&mut b).unwrap(), 5);
+        assert_eq!(&b, b"abcab");
+        let mut b = [0; 4];
+        assert_eq!(r.read(&mut b).unwrap(), 4);
+        assert_eq!(&b, b"cabc");
+        assert_eq!(r.read(&mut []).unwrap(), 0);
+        let mut b = [0; 1];
+        r.read_exact(&mut b).unwrap();
+        assert_eq!(&b, b"a");
+
+        let mut v = Vec::new();
+        repeat_pattern(&[1, 2]).take(7).read_to_end(&mut v).unwrap();
+        assert_eq!(v, [1u8, 2, 1, 2, 1, 2, 1]);
+    }
+
+    #[test]
+    fn repeat_fill_buf() {
+        let mut r = repeat(7);
+        assert!(r.fill_buf().unwrap().iter().all(|b| *b == 7));
+        assert!(!r.fill_buf().unwrap().is_empty());
+        r.consume(10);
+        let mut v = Vec::new();
+        r.take(1000).read_until(0, &mut v).unwrap();
+        assert_eq!(v.len(), 1000);
+        assert!(v.iter().all(|b| *b == 7));
+    }
+
     #[test]
     fn take_some_bytes() {
         assert_eq!(repeat(4).take(100).bytes().count(), 100);
@@ -195,7 +1123,6 @@ mod tests {
     }
 
     #[test]
-    #[allow(deprecated)]
     fn tee() {
         let mut buf = [0; 10];
         {
@@ -206,7 +1133,6 @@ mod tests {
     }
 
     #[test]
-    #[allow(deprecated)]
     fn broadcast() {
         let mut buf1 = [0; 10];
         let mut buf2 = [0; 10];
@@ -23,6 +23,16 @@ pub unsafe fn do_usercall(nr: u64, p1: u64, p2: u64, p3: u64, p4: u64) -> u64 {
 	usercall(nr,p1,p2,0,p3,p4)
 }
 
+/// Usercall numbers reserved by libenclave. Applications should not use
+/// these numbers for their own usercalls.
+///
+/// `USERCALL_READ` and `USERCALL_WRITE` take a file descriptor, a pointer to
+/// a buffer in user memory and its length. They return the number of bytes
+/// read or written, or a negated errno value on failure. They are used by
+/// `io::stdin`, `io::stdout` and `io::stderr`.
+pub const USERCALL_READ:  u64 = 0x7fff_ffff_ffff_0000;
+pub const USERCALL_WRITE: u64 = 0x7fff_ffff_ffff_0001;
+
 pub use alloc::init_user as init_user_heap;
 
 pub struct UserBox<T: Copy>(*mut T);
@@ -0,0 +1,265 @@
+/*
+ * The Rust SGXS library.
+ *
+ * (C) Copyright 2016 Jethro G. Beekman
+ *
+ * This program is free software; you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation; either version 2 of the License, or (at your option)
+ * any later version.
+ */
+
+//! Compressed SGXS container (`.sgxs.z`)
+//!
+//! SGXS streams for large enclaves contain long runs of zeros, both in
+//! measured zero pages and in the mostly empty measurement headers. The
+//! container compresses these runs:
+//!
+//! ```text
+//! offset size field
+//!      0    8 magic: "SGXSZ\0\0\x02"
+//!      8      records
+//!             end record
+//! ```
+//!
+//! Each record starts with a little-endian 32-bit header. If bit 31 is set,
+//! the record is a run of `header & 0x7fffffff` zero bytes. Otherwise,
+//! `header` bytes of literal data follow. A header of 0 is the end record,
+//! which is followed by the uncompressed size as a little-endian 64-bit
+//! value. Since the size comes last, the container can be written in a
+//! single pass to any writer, such as a pipe.
+
+use std::cmp;
+use std::io::{self,Read,Write,Error as IoError,ErrorKind as IoErrorKind};
+
+use byteorder::{ByteOrder,LittleEndian};
+
+pub const MAGIC: &'static [u8; 8] = b"SGXSZ\0\0\x02";
+
+const ZERO_RUN: u32 = 0x8000_0000;
+const END: u32 = 0;
+const MAX_RECORD: u64 = 0x7fff_ffff;
+/// Shorter zero runs are stored as literals, a record header is 4 bytes
+const MIN_ZERO_RUN: u64 = 16;
+const MAX_LITERAL: usize = 0x10000;
+
+/// Compressing writer. The header is written when the writer is created,
+/// the end record and the uncompressed size are written by `finish`, which
+/// must be called.
+pub struct Compressor<W: Write> {
+	inner: W,
+	literal: Vec<u8>,
+	zeros: u64,
+	size: u64,
+}
+
+impl<W: Write> Compressor<W> {
+	pub fn new(mut inner: W) -> io::Result<Self> {
+		try!(inner.write_all(MAGIC));
+		Ok(Compressor{inner:inner,literal:vec![],zeros:0,size:0})
+	}
+
+	fn write_header(&mut self, header: u32) -> io::Result<()> {
+		let mut buf=[0;4];
+		LittleEndian::write_u32(&mut buf,header);
+		self.inner.write_all(&buf)
+	}
+
+	fn flush_literal(&mut self) -> io::Result<()> {
+		if self.literal.is_empty() { return Ok(()) }
+		let len=self.literal.len() as u32;
+		try!(self.write_header(len));
+		try!(self.inner.write_all(&self.literal));
+		self.literal.clear();
+		Ok(())
+	}
+
+	/// Emit pending zeros, either as a run or as part of the literal
+	fn flush_zeros(&mut self) -> io::Result<()> {
+		if self.zeros<MIN_ZERO_RUN {
+			for _ in 0..self.zeros { try!(self.push_literal(0)) }
+		} else {
+			try!(self.flush_literal());
+			while self.zeros>0 {
+				let n=cmp::min(self.zeros,MAX_RECORD);
+				try!(self.write_header(ZERO_RUN|(n as u32)));
+				self.zeros-=n;
+			}
+		}
+		self.zeros=0;
+		Ok(())
+	}
+
+	fn push_literal(&mut self, byte: u8) -> io::Result<()> {
+		if self.literal.len()==MAX_LITERAL {
+			try!(self.flush_literal());
+		}
+		self.literal.push(byte);
+		Ok(())
+	}
+
+	/// Write out any buffered data, the end record and the uncompressed
+	/// size, returning the inner writer.
+	pub fn finish(mut self) -> io::Result<W> {
+		try!(self.flush_zeros());
+		try!(self.flush_literal());
+		try!(self.write_header(END));
+		let mut buf=[0;8];
+		LittleEndian::write_u64(&mut buf,self.size);
+		try!(self.inner.write_all(&buf));
+		try!(self.inner.flush());
+		Ok(self.inner)
+	}
+}
+
+impl<W: Write> Write for Compressor<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		for &byte in buf {
+			if byte==0 {
+				self.zeros+=1;
+			} else {
+				try!(self.flush_zeros());
+				try!(self.push_literal(byte));
+			}
+		}
+		self.size+=buf.len() as u64;
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// Decompressing reader. The uncompressed size in the trailer is checked
+/// when the end record is reached.
+pub struct Decompressor<R: Read> {
+	inner: R,
+	read: u64,
+	zeros: u64,
+	literal: u64,
+	done: bool,
+}
+
+fn invalid_data(msg: &'static str) -> IoError {
+	IoError::new(IoErrorKind::InvalidData,msg)
+}
+
+impl<R: Read> Decompressor<R> {
+	pub fn new(mut inner: R) -> io::Result<Self> {
+		let mut magic=[0;8];
+		try!(inner.read_exact(&mut magic));
+		if &magic!=MAGIC {
+			return Err(invalid_data("not a compressed SGXS stream"));
+		}
+		Ok(Decompressor{inner:inner,read:0,zeros:0,literal:0,done:false})
+	}
+
+	fn read_exact_or_truncated(&mut self, buf: &mut [u8]) -> io::Result<()> {
+		self.inner.read_exact(buf).map_err(|e|match e.kind() {
+			IoErrorKind::UnexpectedEof => invalid_data("truncated compressed SGXS stream"),
+			_ => e,
+		})
+	}
+
+	/// Returns `false` at the end record, after checking the size
+	fn next_record(&mut self) -> io::Result<bool> {
+		let mut buf=[0;4];
+		try!(self.read_exact_or_truncated(&mut buf));
+		let header=LittleEndian::read_u32(&buf);
+		if header==END {
+			let mut size=[0;8];
+			try!(self.read_exact_or_truncated(&mut size));
+			if LittleEndian::read_u64(&size)!=self.read {
+				return Err(invalid_data("compressed SGXS stream size mismatch"));
+			}
+			return Ok(false);
+		} else if header&ZERO_RUN!=0 {
+			self.zeros=(header&!ZERO_RUN) as u64;
+		} else {
+			self.literal=header as u64;
+		}
+		Ok(true)
+	}
+}
+
+impl<R: Read> Read for Decompressor<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() || self.done { return Ok(0) }
+		while self.zeros==0 && self.literal==0 {
+			if !try!(self.next_record()) {
+				self.done=true;
+				return Ok(0);
+			}
+		}
+		let n=if self.zeros>0 {
+			let n=cmp::min(self.zeros,buf.len() as u64) as usize;
+			for b in &mut buf[..n] { *b=0 }
+			self.zeros-=n as u64;
+			n
+		} else {
+			let max=cmp::min(self.literal,buf.len() as u64) as usize;
+			let n=try!(self.inner.read(&mut buf[..max]));
+			if n==0 {
+				return Err(invalid_data("truncated compressed SGXS stream"));
+			}
+			self.literal-=n as u64;
+			n
+		};
+		self.read+=n as u64;
+		Ok(n)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read,Write};
+
+	use super::{Compressor,Decompressor};
+
+	#[test]
+	fn round_trip() {
+		let mut data=vec![1u8,2,3];
+		data.extend(vec![0;0x3000]);
+		data.extend((0..0x200).map(|i|(i%7) as u8));
+		data.extend(vec![0;5]);
+
+		// Doesn't need to seek
+		let mut compressor=Compressor::new(vec![]).unwrap();
+		for chunk in data.chunks(100) {
+			compressor.write_all(chunk).unwrap();
+		}
+		let compressed=compressor.finish().unwrap();
+		assert!(compressed.len()<0x400);
+		assert_eq!(&compressed[compressed.len()-8..],&[0x08,0x32,0,0,0,0,0,0]);
+
+		let mut decompressor=Decompressor::new(&compressed[..]).unwrap();
+		let mut out=vec![];
+		decompressor.read_to_end(&mut out).unwrap();
+		assert!(out==data);
+	}
+
+	#[test]
+	fn truncated() {
+		let mut compressor=Compressor::new(vec![]).unwrap();
+		compressor.write_all(&[1;100]).unwrap();
+		let compressed=compressor.finish().unwrap();
+
+		let mut out=vec![];
+		for &cut in &[4,12,50] {
+			assert!(Decompressor::new(&compressed[..compressed.len()-cut]).unwrap().read_to_end(&mut out).is_err());
+		}
+	}
+
+	#[test]
+	fn size_mismatch() {
+		let mut compressor=Compressor::new(vec![]).unwrap();
+		compressor.write_all(&[1;100]).unwrap();
+		let mut compressed=compressor.finish().unwrap();
+		let len=compressed.len();
+		compressed[len-8]=99;
+
+		let mut out=vec![];
+		assert!(Decompressor::new(&compressed[..]).unwrap().read_to_end(&mut out).is_err());
+	}
+}
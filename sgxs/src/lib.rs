@@ -31,6 +31,7 @@ pub mod sgxs;
 mod intelcall;
 pub mod loader;
 pub mod sigstruct;
+pub mod compress;
 
 mod private {
 	pub mod loader {
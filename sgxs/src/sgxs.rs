@@ -11,7 +11,7 @@
 
 use abi::*;
 
-use std::io::{self,Read,Write,Result as IoResult,Error as IoError,ErrorKind as IoErrorKind};
+use std::io::{self,Read,Write,Seek,SeekFrom,Result as IoResult,Error as IoError,ErrorKind as IoErrorKind};
 
 #[derive(Debug)]
 pub enum Error {
@@ -19,6 +19,7 @@ pub enum Error {
 	StreamNotCanonical,
 	InvalidMeasTag,
 	InvalidPageOffset,
+	DuplicatePage(u64),
 }
 
 impl From<IoError> for Error {
@@ -248,6 +249,127 @@ impl<'a, R: SgxsRead + 'a> PageReader<'a,R> {
 			}
 		}
 	}
+
+	/// Write a hash manifest of the remaining pages to `writer`, to support
+	/// verifying pages individually. For every page with at least one
+	/// measured chunk, an entry consisting of the page offset (8 bytes,
+	/// little-endian) and the SHA-256 hash of the page data (32 bytes) is
+	/// written.
+	///
+	/// Returns the number of entries written.
+	pub fn write_page_hashes<W: Write>(&mut self, writer: &mut W) -> Result<usize> {
+		use byteorder::{ByteOrder,LittleEndian};
+		use crypto::{Sha256Digest,Sha256};
+
+		let mut entries=0;
+		while let Some((eadd,chunks,page))=try!(self.read_page()) {
+			if chunks.0==0 { continue }
+			let mut hasher=<Sha256 as Sha256Digest>::new();
+			try!(hasher.write_all(&page));
+			let mut offset=[0u8;8];
+			LittleEndian::write_u64(&mut offset,eadd.offset);
+			try!(writer.write_all(&offset));
+			try!(writer.write_all(&hasher.finish()));
+			entries+=1;
+		}
+		Ok(entries)
+	}
+}
+
+/// The `SgxsIndex` struct provides random access to the pages of a seekable
+/// SGXS stream.
+///
+/// The stream is read once when the index is created, recording the stream
+/// position of every EADD blob. Afterwards, individual pages can be read by
+/// address without reading the rest of the stream.
+pub struct SgxsIndex<'a, R: Read + Seek + 'a> {
+	reader: &'a mut R,
+	ecreate: MeasECreate,
+	// (page offset, stream position of the EADD blob), sorted by page offset
+	pages: Vec<(u64,u64)>,
+}
+
+impl<'a, R: Read + Seek + 'a> SgxsIndex<'a,R> {
+	pub fn new(reader: &'a mut R) -> Result<Self> {
+		let mut cread=CanonicalSgxsReader::new(reader);
+		let ecreate=match try!(cread.read_meas()) {
+			Some(Meas::ECreate(header)) => header,
+			_ => return Err(Error::StreamNotCanonical)
+		};
+
+		let mut pages=vec![];
+		loop {
+			let pos=try!(cread.reader.seek(SeekFrom::Current(0)));
+			match try!(cread.read_meas()) {
+				Some(Meas::EAdd(header)) => pages.push((header.offset,pos)),
+				Some(_) => {},
+				None => break,
+			}
+		}
+
+		Ok(SgxsIndex{reader:cread.reader,ecreate:ecreate,pages:pages})
+	}
+
+	pub fn ecreate(&self) -> &MeasECreate {
+		&self.ecreate
+	}
+
+	/// Read the page at enclave offset `offset`. Returns `None` if the stream
+	/// doesn't contain a page at that offset.
+	pub fn read_page_at(&mut self, offset: u64) -> Result<Option<(MeasEAdd,PageChunks,[u8;4096])>> {
+		let pos=match self.pages.binary_search_by(|&(page,_)|page.cmp(&offset)) {
+			Ok(i) => self.pages[i].1,
+			Err(_) => return Ok(None),
+		};
+		try!(self.reader.seek(SeekFrom::Start(pos)));
+
+		// The ECREATE blob has already been checked when building the index
+		let cread=CanonicalSgxsReader {
+			reader: &mut *self.reader,
+			got_ecreate: true,
+			last_offset: None,
+			chunks_measured: PageChunks(0),
+		};
+		PageReader{reader:cread,last_eadd:None}.read_page()
+	}
+}
+
+/// Check whether `sgxs` is a canonical SGXS stream (see
+/// `CanonicalSgxsReader`) with valid SECINFO for every page. The SECINFO of a
+/// page is valid if the page type is REG with any of the R, W and X flags, or
+/// TCS with no other flags.
+///
+/// Returns `Ok(false)` if the stream is not canonical. Errors are returned if
+/// the stream could not be read or contains invalid measurement blobs.
+pub fn is_canonical<R: Read>(mut sgxs: R) -> Result<bool> {
+	use abi::{PageType,secinfo_flags};
+
+	let mut cread=CanonicalSgxsReader::new(&mut sgxs);
+	match cread.read_meas() {
+		Ok(Some(Meas::ECreate(_))) => {},
+		Ok(_) | Err(Error::StreamNotCanonical) => return Ok(false),
+		Err(e) => return Err(e),
+	}
+
+	loop {
+		match cread.read_meas() {
+			Ok(Some(Meas::EAdd(header))) => {
+				let flags=header.secinfo.flags;
+				let allowed=if flags.page_type()==PageType::Reg as u8 {
+					secinfo_flags::PT_MASK|secinfo_flags::R|secinfo_flags::W|secinfo_flags::X
+				} else if flags.page_type()==PageType::Tcs as u8 {
+					secinfo_flags::PT_MASK
+				} else {
+					return Ok(false)
+				};
+				if !(flags&!allowed).is_empty() { return Ok(false) }
+			},
+			Ok(Some(_)) => {},
+			Ok(None) => return Ok(true),
+			Err(Error::StreamNotCanonical) => return Ok(false),
+			Err(e) => return Err(e),
+		}
+	}
 }
 
 pub type MeasuredData<'a,R>=Option<&'a mut R>;
@@ -351,6 +473,63 @@ impl<'a, W: SgxsWrite + 'a> CanonicalSgxsWriter<'a,W> {
 	}
 }
 
+/// The `SortingSgxsWriter` struct buffers pages that are written in any order.
+///
+/// When calling `finish`, the pages are sorted by address and written to a
+/// `CanonicalSgxsWriter`. Gaps between pages are allowed, but every page
+/// address may only be written once. Measured page data is kept in memory
+/// until `finish` is called.
+pub struct SortingSgxsWriter {
+	pages: Vec<(u64,SecinfoTruncated,Option<Vec<u8>>)>,
+}
+
+impl SortingSgxsWriter {
+	pub fn new() -> Self {
+		SortingSgxsWriter { pages: vec![] }
+	}
+
+	pub fn write_page<R: Read>(&mut self, data: MeasuredData<R>, offset: u64, secinfo: SecinfoTruncated) -> Result<()> {
+		if offset&0xfff!=0 { return Err(Error::InvalidPageOffset) }
+		let data=match data {
+			Some(reader) => {
+				let mut buf=vec![0;4096];
+				try!(reader.chain(io::repeat(0)).read_exact(&mut buf));
+				Some(buf)
+			},
+			None => None,
+		};
+		self.pages.push((offset,secinfo,data));
+		Ok(())
+	}
+
+	pub fn write_pages<R: Read>(&mut self, mut data: MeasuredData<R>, n: usize, offset: u64, secinfo: SecinfoTruncated) -> Result<()> {
+		for i in 0..(n as u64) {
+			try!(self.write_page(data.as_mut(),offset+4096*i,secinfo.clone()));
+		}
+		Ok(())
+	}
+
+	/// Write the ECREATE and all buffered pages in canonical order.
+	///
+	/// Returns `Error::DuplicatePage` if a page address was written more than
+	/// once. Nothing is written to `writer` in that case.
+	pub fn finish<W: SgxsWrite>(mut self, writer: &mut W, ecreate: MeasECreate) -> Result<()> {
+		self.pages.sort_by(|a,b|a.0.cmp(&b.0));
+		for pair in self.pages.windows(2) {
+			if pair[0].0==pair[1].0 { return Err(Error::DuplicatePage(pair[1].0)) }
+		}
+
+		let mut writer=try!(CanonicalSgxsWriter::new(writer,ecreate));
+		for (offset,secinfo,data) in self.pages {
+			match data {
+				Some(data) => try!(writer.write_page(Some(&mut &data[..]),Some(offset),secinfo)),
+				None => try!(writer.write_page::<&[u8]>(None,Some(offset),secinfo)),
+			}
+		}
+		Ok(())
+	}
+}
+
 /// Note: only the first 48 bytes of the `Secinfo` structure are included in a
 /// `Meas` blob.
 #[repr(C,packed)]
@@ -378,3 +557,132 @@ pub struct MeasEAdd {
 pub struct MeasEExtend {
 	pub offset: u64,
 }
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use abi::{PageType,secinfo_flags};
+	use super::{CanonicalSgxsWriter,SortingSgxsWriter,PageReader,SgxsIndex,SgxsWrite,Meas,MeasECreate,MeasEAdd,SecinfoTruncated,Error,is_canonical};
+
+	fn multi_page_sgxs() -> Vec<u8> {
+		let mut buf=vec![];
+		{
+			let mut writer=CanonicalSgxsWriter::new(&mut buf,MeasECreate{size:0x10000,ssaframesize:1}).unwrap();
+			let secinfo=SecinfoTruncated{flags:secinfo_flags::R|secinfo_flags::W|PageType::Reg.into()};
+			for i in 0..4u8 {
+				let data=[i+1;4096];
+				writer.write_page(Some(&mut &data[..]),Some((i as u64)*0x2000),secinfo.clone()).unwrap();
+			}
+			writer.write_pages::<&[u8]>(None,2,None,secinfo).unwrap();
+		}
+		buf
+	}
+
+	#[test]
+	fn index_read_page_at() {
+		let buf=multi_page_sgxs();
+
+		let mut full=&buf[..];
+		let (_,mut preader)=PageReader::new(&mut full).unwrap();
+		let mut pages=vec![];
+		while let Some(page)=preader.read_page().unwrap() {
+			pages.push(page);
+		}
+		assert_eq!(pages.len(),6);
+
+		let mut cursor=Cursor::new(&buf[..]);
+		let mut index=SgxsIndex::new(&mut cursor).unwrap();
+		assert_eq!({index.ecreate().size},0x10000);
+		for &(ref eadd,chunks,ref data) in pages.iter().rev() {
+			let (ieadd,ichunks,idata)=index.read_page_at(eadd.offset).unwrap().unwrap();
+			assert_eq!({ieadd.offset},{eadd.offset});
+			assert_eq!(ichunks,chunks);
+			assert_eq!(&idata[..],&data[..]);
+		}
+		assert!(index.read_page_at(0x1000).unwrap().is_none());
+	}
+
+	#[test]
+	fn page_hashes() {
+		use std::io::Write;
+		use byteorder::{ByteOrder,LittleEndian};
+		use crypto::{Sha256Digest,Sha256};
+
+		let buf=multi_page_sgxs();
+		let mut reader=&buf[..];
+		let (_,mut preader)=PageReader::new(&mut reader).unwrap();
+		let mut manifest=vec![];
+		assert_eq!(preader.write_page_hashes(&mut manifest).unwrap(),4);
+		assert_eq!(manifest.len(),4*40);
+
+		for (i,entry) in manifest.chunks(40).enumerate() {
+			let mut offset=[0u8;8];
+			LittleEndian::write_u64(&mut offset,(i as u64)*0x2000);
+			assert_eq!(&entry[..8],&offset[..]);
+			let mut hasher=<Sha256 as Sha256Digest>::new();
+			hasher.write_all(&[i as u8+1;4096]).unwrap();
+			assert_eq!(&entry[8..],&hasher.finish()[..]);
+		}
+	}
+
+	#[test]
+	fn sorting_writer() {
+		let secinfo=SecinfoTruncated{flags:secinfo_flags::R|secinfo_flags::W|PageType::Reg.into()};
+		let mut sorting=SortingSgxsWriter::new();
+		sorting.write_pages::<&[u8]>(None,2,0x7000,secinfo.clone()).unwrap();
+		for i in (0..4u8).rev() {
+			let data=[i+1;4096];
+			sorting.write_page(Some(&mut &data[..]),(i as u64)*0x2000,secinfo.clone()).unwrap();
+		}
+		let mut buf=vec![];
+		sorting.finish(&mut buf,MeasECreate{size:0x10000,ssaframesize:1}).unwrap();
+		assert!(buf==multi_page_sgxs());
+
+		let mut sorting=SortingSgxsWriter::new();
+		sorting.write_page::<&[u8]>(None,0x1000,secinfo.clone()).unwrap();
+		sorting.write_page::<&[u8]>(None,0,secinfo.clone()).unwrap();
+		sorting.write_page::<&[u8]>(None,0x1000,secinfo.clone()).unwrap();
+		let mut buf=vec![];
+		match sorting.finish(&mut buf,MeasECreate{size:0x10000,ssaframesize:1}) {
+			Err(Error::DuplicatePage(0x1000)) => {},
+			other => panic!("unexpected result {:?}",other),
+		}
+		assert!(buf.is_empty());
+	}
+
+	#[test]
+	fn canonical() {
+		assert!(is_canonical(&multi_page_sgxs()[..]).unwrap());
+	}
+
+	#[test]
+	fn not_canonical() {
+		let secinfo=SecinfoTruncated{flags:secinfo_flags::R|PageType::Reg.into()};
+		let ecreate=Meas::ECreate(MeasECreate{size:0x10000,ssaframesize:1});
+
+		// Descending page offsets
+		let mut buf=vec![];
+		buf.write_meas(&ecreate).unwrap();
+		buf.write_page::<&[u8]>(None,0x1000,secinfo.clone()).unwrap();
+		buf.write_page::<&[u8]>(None,0,secinfo.clone()).unwrap();
+		assert!(!is_canonical(&buf[..]).unwrap());
+
+		// No ECREATE
+		let mut buf=vec![];
+		buf.write_page::<&[u8]>(None,0,secinfo.clone()).unwrap();
+		assert!(!is_canonical(&buf[..]).unwrap());
+
+		// Invalid page type
+		let mut buf=vec![];
+		buf.write_meas(&ecreate).unwrap();
+		buf.write_meas(&Meas::EAdd(MeasEAdd{offset:0,secinfo:SecinfoTruncated{flags:PageType::Va.into()}})).unwrap();
+		assert!(!is_canonical(&buf[..]).unwrap());
+
+		// Permissions on a TCS page
+		let mut buf=vec![];
+		buf.write_meas(&ecreate).unwrap();
+		buf.write_meas(&Meas::EAdd(MeasEAdd{offset:0,secinfo:SecinfoTruncated{flags:secinfo_flags::R|PageType::Tcs.into()}})).unwrap();
+		assert!(!is_canonical(&buf[..]).unwrap());
+	}
+}